@@ -4,7 +4,8 @@ use crate::*;
 pub struct Actor {
     pub position: Vector2,
     pub is_child: bool,
-    remainder: Vector2,
+    // Sub-pixel movement not applied to the position yet
+    pub remainder: Vector2,
     width: f32,
     height: f32,
     carried_by_player: bool,
@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use crate::*;
 
 pub type ActorId = u32;
@@ -7,14 +5,15 @@ pub type ActorId = u32;
 #[turbo::serialize]
 pub struct ActorManager {
     next_id: ActorId,
-    pub actors: HashMap<ActorId, Actor>,
+    // Ordered by id so every peer walks the actors in the same order
+    pub actors: BTreeMap<ActorId, Actor>,
 }
 
 impl ActorManager {
     pub fn new() -> Self {
         Self {
             next_id: 0,
-            actors: HashMap::new(),
+            actors: BTreeMap::new(),
         }
     } 
     
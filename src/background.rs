@@ -17,7 +17,13 @@ impl Background {
     pub fn draw(&self, camera_center: Vector2) {
         clear(self.default_color);
         for layer in &self.layers {
-            sprite!(layer.sprite.as_str(), x = layer.offset_x + (camera_center.x * layer.speed_x) as i32, y = layer.offset_y);
+            // Vertical scrolling is measured from the topmost camera position so short levels are unaffected
+            let camera_top = camera_center.y - SCREEN_HEIGHT as f32 / 2.;
+            sprite!(
+                layer.sprite.as_str(),
+                x = layer.offset_x + (camera_center.x * layer.speed_x) as i32,
+                y = layer.offset_y + (camera_top * layer.speed_y) as i32,
+            );
         }
     }
 }
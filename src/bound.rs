@@ -19,6 +19,34 @@ impl fmt::Display for BoundingBox {
 }
 
 impl BoundingBox {
+    pub fn from_center(center: &Vector2, width: f32, height: f32) -> Self {
+        BoundingBox {
+            top: center.y - height / 2.,
+            right: center.x + width / 2.,
+            bottom: center.y + height / 2.,
+            left: center.x - width / 2.,
+        }
+    }
+
+    pub fn expand(&self, margin: f32) -> BoundingBox {
+        BoundingBox {
+            top: self.top - margin,
+            right: self.right + margin,
+            bottom: self.bottom + margin,
+            left: self.left - margin,
+        }
+    }
+
+    // Overlapping area of two boxes, only meaningful if they intersect
+    pub fn intersection(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            top: self.top.max(other.top),
+            right: self.right.min(other.right),
+            bottom: self.bottom.min(other.bottom),
+            left: self.left.max(other.left),
+        }
+    }
+
     pub fn intersects(&self, other: &BoundingBox) -> bool {
         !(
             self.left >= other.right ||
@@ -29,7 +57,7 @@ impl BoundingBox {
     }
     
     pub fn contains(&self, point: Vector2) -> bool {
-        point.x < self.right && point.x > self.left
+        point.x < self.right && point.x > self.left && point.y < self.bottom && point.y > self.top
     }
     
     pub fn draw_bounding_box(&self) {
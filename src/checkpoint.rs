@@ -0,0 +1,58 @@
+use crate::*;
+
+#[turbo::serialize]
+pub struct Checkpoint {
+    pub id: u32,
+    pub solid: Solid,
+    pub is_reached: bool,
+}
+
+impl Checkpoint {
+    pub fn new(id: u32, position_x: f32, position_y: f32, width: f32, height: f32) -> Self {
+        Self {
+            id,
+            solid: Solid {
+                position: Vector2 { x: position_x, y: position_y },
+                width,
+                height,
+            },
+            is_reached: false,
+        }
+    }
+
+    pub fn draw(&self) {
+        let BoundingBox { top, right: _, bottom, left } = self.solid.get_bound();
+        let pole_x = left as i32 + 2;
+        let flag_color = if self.is_reached { 0x4be5feff } else { 0x777777ff };
+
+        rect!(
+            w = 2,
+            h = (bottom - top) as u32,
+            x = pole_x,
+            y = top as i32,
+            color = 0x555566ff,
+        );
+        rect!(
+            w = 10,
+            h = 7,
+            x = pole_x + 2,
+            y = top as i32 + 1,
+            color = flag_color,
+        );
+    }
+
+    pub fn draw_bounding_box(&self) {
+        self.solid.get_bound().draw_bounding_box();
+    }
+}
+
+// State of the level at the moment a checkpoint was reached
+#[turbo::serialize]
+pub struct CheckpointSnapshot {
+    pub checkpoint_id: u32,
+    pub respawn_position: Vector2,
+    pub harvesters: Vec<Harvester>,
+    pub actor_manager: ActorManager,
+    // Door states by index in the tilemap, so a respawn doesn't leave them open or shut
+    pub open_doors: Vec<bool>,
+}
@@ -0,0 +1,62 @@
+use crate::*;
+
+// Once per second is enough to notice a desync without adding to every snapshot
+pub const CHECKSUM_INTERVAL_TICKS: u32 = 1000 / SERVER_TICK_MS;
+
+// Each part of the state is hashed on its own so a mismatch can tell which one diverged first.
+// Only state the client predicts on its own is hashed, the rest of the world is moved by the
+// partner as well and only reaches the client through snapshots.
+const CHECKSUM_FIELDS: [&str; 5] = [
+    "player position",
+    "player actor",
+    "player exposure",
+    "player",
+    "carried item",
+];
+
+// FNV-1a over the borsh bytes, so floats are compared bit for bit
+fn hash<T: borsh::BorshSerialize>(value: &T) -> u32 {
+    let bytes = borsh::to_vec(value).unwrap_or_default();
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x0100_0193))
+}
+
+// Hash of the state one player's input decides, sent by the server
+// and compared by the client against what it predicted for the same input
+#[turbo::serialize]
+#[derive(PartialEq)]
+pub struct StateChecksum {
+    // Level clock the state was hashed at. The server may run held input the client never
+    // predicted, a prediction at another level frame is a different state and can't be compared.
+    pub level_frame: u32,
+    hashes: Vec<u32>,
+}
+
+// Outcome of comparing a server checksum with the prediction for the same input and level frame
+#[derive(Debug, PartialEq)]
+pub enum ChecksumCheck {
+    Matched,
+    Desynced(&'static str),
+}
+
+impl StateChecksum {
+    pub fn capture(level_frame: u32, player: &Player, actor_manager: &ActorManager) -> Self {
+        let carried_item = player.get_picked_item().and_then(|id| actor_manager.get_actor(id));
+        Self {
+            level_frame,
+            hashes: vec![
+                hash(&player.actor.position),
+                hash(&player.actor),
+                hash(&player.exposure),
+                hash(player),
+                hash(&carried_item),
+            ],
+        }
+    }
+
+    pub fn get_first_divergence(&self, other: &StateChecksum) -> Option<&'static str> {
+        CHECKSUM_FIELDS.iter()
+            .zip(self.hashes.iter().zip(&other.hashes))
+            .find(|(_, (a, b))| a != b)
+            .map(|(field, _)| *field)
+    }
+}
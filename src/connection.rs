@@ -0,0 +1,39 @@
+use crate::*;
+
+// Two seconds without a server update means our own connection dropped
+pub const CONNECTION_TIMEOUT_FRAMES: u32 = 120;
+// A disconnected player keeps their slot this long before it is released
+pub const RECONNECT_GRACE_TICKS: u32 = 30_000 / SERVER_TICK_MS;
+
+#[turbo::serialize]
+#[derive(PartialEq)]
+pub enum ConnectionStatus {
+    Connected,
+    // We lost the server and wait for the channel to come back
+    Reconnecting,
+    // The partner dropped, the server holds the game until they are back
+    PartnerDisconnected,
+    // The partner didn't come back within the grace period
+    PartnerLeft,
+}
+
+impl ConnectionStatus {
+    pub fn is_paused(&self) -> bool {
+        *self != ConnectionStatus::Connected
+    }
+}
+
+pub fn draw_connection_overlay(status: &ConnectionStatus, screen_center: &Vector2) {
+    let (title, hint) = match status {
+        ConnectionStatus::Connected => return,
+        ConnectionStatus::Reconnecting => ("Connection lost", "Reconnecting..."),
+        ConnectionStatus::PartnerDisconnected => ("Partner disconnected", "Waiting for them to return..."),
+        ConnectionStatus::PartnerLeft => ("Partner left the game", "Confirm: wait for someone new  Back: leave"),
+    };
+
+    let left = screen_center.x as i32 - SCREEN_WIDTH / 2;
+    let top = screen_center.y as i32 - SCREEN_HEIGHT / 2;
+    rect!(x = left, y = top, w = SCREEN_WIDTH, h = SCREEN_HEIGHT, color = 0x000000aa);
+    text!(title, x = screen_center.x as i32 - title.len() as i32 * 4, y = screen_center.y as i32 - 12, color = 0xffffffff, font = "large");
+    text!(hint, x = screen_center.x as i32 - hint.len() as i32 * 4, y = screen_center.y as i32 + 4, color = 0x777777ff, font = "large");
+}
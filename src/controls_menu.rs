@@ -0,0 +1,103 @@
+use crate::*;
+
+const ROW_HEIGHT: i32 = 16;
+const FIRST_ROW_Y: i32 = 40;
+// Waiting for a new binding gives up after this, in case nothing usable can be pressed
+const LISTEN_TIMEOUT_FRAMES: u32 = 5 * 60;
+
+// Screen for remapping the bindings of the keyboard and the gamepads. Player 1 navigates it,
+// the bindings of either local player can be edited.
+#[turbo::serialize]
+pub struct ControlsMenu {
+    selected_index: usize,
+    // 0 for player 1, 1 for player 2
+    player_index: usize,
+    // Frames left to press the new input, 0 while not listening
+    listen_timer: u32,
+}
+
+impl ControlsMenu {
+    pub fn new() -> Self {
+        Self {
+            selected_index: 0,
+            player_index: 0,
+            listen_timer: 0,
+        }
+    }
+
+    // Rows are all actions followed by the reset option
+    fn get_row_count(&self) -> usize {
+        Action::all().len() + 1
+    }
+
+    // Returns true once the player leaves the screen
+    pub fn handle_input(&mut self, input_map: &mut InputMap, input_map2: &mut InputMap) -> bool {
+        if self.listen_timer > 0 {
+            // Escape and Start cancel so a binding can't get stuck waiting for input, neither can be bound
+            let is_cancelled = keyboard::get().escape().just_pressed()
+                || gamepad::get(input_map.gamepad_index).start.just_pressed()
+                || gamepad::get(input_map2.gamepad_index).start.just_pressed();
+            if is_cancelled {
+                self.listen_timer = 0;
+                return false;
+            }
+            self.listen_timer -= 1;
+            let edited_input_map = if self.player_index == 0 { input_map } else { input_map2 };
+            if let Some(source) = edited_input_map.get_just_pressed_source() {
+                let action = Action::all()[self.selected_index];
+                edited_input_map.rebind(action, source);
+                self.listen_timer = 0;
+            }
+            return false;
+        }
+
+        if input_map.just_pressed(Action::Left) || input_map.just_pressed(Action::Right) {
+            self.player_index = 1 - self.player_index;
+        }
+        if input_map.just_pressed(Action::Up) {
+            self.selected_index = (self.selected_index + self.get_row_count() - 1) % self.get_row_count();
+        }
+        if input_map.just_pressed(Action::Down) {
+            self.selected_index = (self.selected_index + 1) % self.get_row_count();
+        }
+        if input_map.just_pressed(Action::Confirm) {
+            if self.selected_index < Action::all().len() {
+                self.listen_timer = LISTEN_TIMEOUT_FRAMES;
+            } else if self.player_index == 0 {
+                input_map.bindings = input_map.get_default_bindings();
+            } else {
+                input_map2.bindings = input_map2.get_default_bindings();
+            }
+            return false;
+        }
+
+        input_map.just_pressed(Action::Back)
+    }
+
+    pub fn draw(&self, input_map: &InputMap, input_map2: &InputMap) {
+        let title = format!("CONTROLS - PLAYER {}", self.player_index + 1);
+        text!(&title, x = SCREEN_WIDTH / 2 - title.len() as i32 * 4, y = 16, color = 0xffffffff, font = "large");
+
+        let edited_input_map = if self.player_index == 0 { input_map } else { input_map2 };
+        for (idx, action) in Action::all().into_iter().enumerate() {
+            let sources = if self.listen_timer > 0 && idx == self.selected_index {
+                format!("Press a key or button... {}", self.listen_timer / 60 + 1)
+            } else {
+                edited_input_map.get_sources(action).iter().map(|source| source.to_string()).collect::<Vec<_>>().join(" / ")
+            };
+            let action_name = action.to_string();
+            let y = FIRST_ROW_Y + idx as i32 * ROW_HEIGHT;
+            let color = self.get_row_color(idx);
+            text!(&action_name, x = 40, y = y, color = color, font = "large");
+            text!(&sources, x = 170, y = y, color = color, font = "large");
+        }
+
+        let reset_y = FIRST_ROW_Y + Action::all().len() as i32 * ROW_HEIGHT + 8;
+        text!("Reset to defaults", x = 40, y = reset_y, color = self.get_row_color(Action::all().len()), font = "large");
+        text!("Left/Right: player  Esc/Start: cancel  Back: leave", x = 40, y = SCREEN_HEIGHT - 24, color = 0x777777ff, font = "large");
+    }
+
+    fn get_row_color(&self, idx: usize) -> u32 {
+        if idx == self.selected_index { 0x4be5feff } else { 0xffffffff }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::*;
+
+// Players closer than this to the edge of the screen count as out of frame
+const FRAMING_MARGIN: f32 = 40.;
+const INDICATOR_EDGE_OFFSET: f32 = 18.;
+const INDICATOR_SIZE: f32 = 10.;
+const INDICATOR_COLOR: u32 = 0xffb070ff;
+const PIXELS_PER_METER: f32 = 16.;
+
+// Largest distance between same-screen players that still fits in the frame
+const LEASH_WIDTH: f32 = SCREEN_WIDTH as f32 - 2. * FRAMING_MARGIN;
+const LEASH_HEIGHT: f32 = SCREEN_HEIGHT as f32 - 2. * FRAMING_MARGIN;
+
+// Center of the box around all players, so a straggler pulls the frame as much as the group
+pub fn get_group_camera_position(tilemap: &TileMap, local_position: &Vector2, partner_positions: &[Vector2]) -> Vector2 {
+    let mut min = *local_position;
+    let mut max = *local_position;
+    for position in partner_positions {
+        min = Vector2::new(min.x.min(position.x), min.y.min(position.y));
+        max = Vector2::new(max.x.max(position.x), max.y.max(position.y));
+    }
+    let viewport = Vector2::new(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+    let center = (min + max) * 0.5;
+    tilemap.lock_viewport_to_tilemap(&center, &viewport)
+}
+
+// Camera center that keeps every player on screen, None when they are too far apart to fit
+pub fn get_shared_camera_position(tilemap: &TileMap, local_position: &Vector2, partner_positions: &[Vector2]) -> Option<Vector2> {
+    if partner_positions.is_empty() {
+        return None;
+    }
+
+    let viewport = Vector2::new(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+    let camera_position = get_group_camera_position(tilemap, local_position, partner_positions);
+    let framed_bound = BoundingBox::from_center(&camera_position, viewport.x, viewport.y).expand(-FRAMING_MARGIN);
+    if framed_bound.contains(*local_position) && partner_positions.iter().all(|position| framed_bound.contains(*position)) {
+        Some(camera_position)
+    } else {
+        None
+    }
+}
+
+pub fn is_within_leash(position: &Vector2, partner_position: &Vector2) -> bool {
+    (position.x - partner_position.x).abs() <= LEASH_WIDTH && (position.y - partner_position.y).abs() <= LEASH_HEIGHT
+}
+
+// Same-screen co-op has no camera to fall back to, so nobody may leave the shared frame.
+// Whoever walked away is pulled back along the path they just took, which can't be blocked.
+// When that isn't enough, e.g. one climbed out of reach, the lower player joins the upper one.
+pub fn leash_local_players(player: &mut Player, partner: &mut Player, previous_positions: &[Vector2; 2]) {
+    let positions = [player.get_position(), partner.get_position()];
+    let excess_x = (positions[0].x - positions[1].x).abs() - LEASH_WIDTH;
+    if excess_x > 0. {
+        let directions = [(positions[0].x - positions[1].x).signum(), (positions[1].x - positions[0].x).signum()];
+        let moved_away = [0, 1].map(|idx| ((positions[idx].x - previous_positions[idx].x) * directions[idx]).max(0.));
+        let total_moved_away = moved_away[0] + moved_away[1];
+        if total_moved_away > 0. {
+            for (idx, leashed_player) in [&mut *player, &mut *partner].into_iter().enumerate() {
+                let pull = (excess_x * moved_away[idx] / total_moved_away).ceil().min(moved_away[idx]);
+                if pull > 0. {
+                    leashed_player.shift(&Vector2::new(-directions[idx] * pull, 0.));
+                }
+            }
+        }
+    }
+
+    let positions = [player.get_position(), partner.get_position()];
+    if !is_within_leash(&positions[0], &positions[1]) {
+        let (lower_player, upper_position) = if positions[0].y > positions[1].y { (player, positions[1]) } else { (partner, positions[0]) };
+        let offset = upper_position - lower_player.get_position();
+        lower_player.shift(&offset);
+    }
+}
+
+// Arrow on the edge of the screen pointing at an off-screen partner, with the distance next to it
+pub fn draw_partner_indicator(screen_center: &Vector2, local_position: &Vector2, partner_position: &Vector2) {
+    let viewport_bound = BoundingBox::from_center(screen_center, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+    if viewport_bound.contains(*partner_position) {
+        return;
+    }
+
+    let direction = (*partner_position - *screen_center).normalize();
+    let half_width = SCREEN_WIDTH as f32 / 2. - INDICATOR_EDGE_OFFSET;
+    let half_height = SCREEN_HEIGHT as f32 / 2. - INDICATOR_EDGE_OFFSET;
+    // Stretch the direction until it hits the closer screen edge
+    let edge_scale = (half_width / direction.x.abs()).min(half_height / direction.y.abs());
+    let tip = *screen_center + direction * edge_scale;
+
+    let base = tip - direction * INDICATOR_SIZE;
+    let side = direction.get_normal_vector() * (INDICATOR_SIZE / 2.);
+    let left_corner = base + side;
+    let right_corner = base - side;
+    for (start, end) in [(tip, left_corner), (left_corner, right_corner), (right_corner, tip)] {
+        path!(
+            start = (start.x, start.y),
+            end = (end.x, end.y),
+            width = 2,
+            color = INDICATOR_COLOR,
+        );
+    }
+
+    let distance = (*partner_position - *local_position).length() / PIXELS_PER_METER;
+    let label = format!("{}m", distance as i32);
+    let label_position = tip - direction * (INDICATOR_SIZE * 2.5);
+    text!(
+        &label,
+        x = label_position.x as i32 - label.len() as i32 * 2,
+        y = label_position.y as i32 - 3,
+        color = INDICATOR_COLOR,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walking_away_is_pulled_back_to_the_leash() {
+        let mut player = Player::new(100., 200.);
+        let mut partner = Player::new(100. + LEASH_WIDTH, 200.);
+        let previous_positions = [player.get_position(), partner.get_position()];
+        partner.shift(&Vector2::new(6., 0.));
+
+        leash_local_players(&mut player, &mut partner, &previous_positions);
+
+        assert_eq!(player.get_position().x, 100.);
+        assert_eq!(partner.get_position().x, 100. + LEASH_WIDTH);
+    }
+
+    #[test]
+    fn players_within_the_leash_move_freely() {
+        let mut player = Player::new(100., 200.);
+        let mut partner = Player::new(150., 200.);
+        let previous_positions = [player.get_position(), partner.get_position()];
+        partner.shift(&Vector2::new(6., 0.));
+
+        leash_local_players(&mut player, &mut partner, &previous_positions);
+
+        assert_eq!(partner.get_position().x, 156.);
+    }
+
+    #[test]
+    fn player_out_of_reach_below_joins_the_upper_one() {
+        let mut player = Player::new(100., 20.);
+        let mut partner = Player::new(120., 40. + LEASH_HEIGHT);
+        let previous_positions = [player.get_position(), partner.get_position()];
+
+        leash_local_players(&mut player, &mut partner, &previous_positions);
+
+        assert_eq!(player.get_position().y, 20.);
+        assert_eq!(partner.get_position().x, 100.);
+        assert_eq!(partner.get_position().y, 20.);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::*;
+
+// In-game overlay for swapping the movement profile of the loaded level while tuning
+#[turbo::serialize]
+pub struct DebugMenu {
+    pub is_open: bool,
+    selected_index: usize,
+    profile_names: Vec<String>,
+}
+
+impl DebugMenu {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            selected_index: 0,
+            profile_names: load_movement_profiles().iter().map(|profile| profile.name.clone()).collect(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.is_open = !self.is_open;
+    }
+
+    // Returns the name of the profile picked by the player
+    pub fn handle_input(&mut self, input_map: &InputMap) -> Option<String> {
+        if self.profile_names.is_empty() {
+            return None;
+        }
+
+        if input_map.just_pressed(Action::Up) {
+            self.selected_index = (self.selected_index + self.profile_names.len() - 1) % self.profile_names.len();
+        }
+        if input_map.just_pressed(Action::Down) {
+            self.selected_index = (self.selected_index + 1) % self.profile_names.len();
+        }
+        if input_map.just_pressed(Action::Confirm) {
+            return Some(self.profile_names[self.selected_index].clone());
+        }
+
+        None
+    }
+
+    pub fn draw(&self, active_profile: &str) {
+        let height = 20 + self.profile_names.len() as u32 * 10;
+        rect!(w = 160, h = height, x = 8, y = 48, color = 0x000000cc, fixed = true);
+        text!("MOVEMENT PROFILE", x = 14, y = 54, fixed = true);
+
+        for (idx, name) in self.profile_names.iter().enumerate() {
+            let cursor = if idx == self.selected_index { ">" } else { " " };
+            let active_marker = if name == active_profile { "*" } else { "" };
+            let color = if idx == self.selected_index { 0x4be5feff } else { 0xffffffff };
+            let line = format!("{} {}{}", cursor, name, active_marker);
+            text!(
+                &line,
+                x = 14,
+                y = 66 + idx as i32 * 10,
+                color = color,
+                fixed = true,
+            );
+        }
+    }
+}
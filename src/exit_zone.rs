@@ -0,0 +1,49 @@
+use crate::*;
+
+#[turbo::serialize]
+pub enum ExitTarget {
+    NextLevel,
+    Level(LevelName),
+    // Leaves the level sequence and returns to the main menu
+    Hub,
+}
+
+// Which players need to be inside the zone for the exit to trigger in co-op
+#[turbo::serialize]
+pub enum ExitRule {
+    AnyPlayer,
+    AllPlayers,
+}
+
+#[turbo::serialize]
+pub struct ExitZone {
+    pub solid: Solid,
+    pub target: ExitTarget,
+    pub rule: ExitRule,
+}
+
+impl ExitZone {
+    pub fn new(position_x: f32, position_y: f32, width: f32, height: f32, target: ExitTarget, rule: ExitRule) -> Self {
+        Self {
+            solid: Solid {
+                position: Vector2 { x: position_x, y: position_y },
+                width,
+                height,
+            },
+            target,
+            rule,
+        }
+    }
+
+    pub fn is_triggered(&self, player_positions: &[Vector2]) -> bool {
+        let bounding_box = self.solid.get_bound();
+        match self.rule {
+            ExitRule::AnyPlayer => player_positions.iter().any(|position| bounding_box.contains(*position)),
+            ExitRule::AllPlayers => !player_positions.is_empty() && player_positions.iter().all(|position| bounding_box.contains(*position)),
+        }
+    }
+
+    pub fn draw_bounding_box(&self) {
+        self.solid.get_bound().draw_bounding_box();
+    }
+}
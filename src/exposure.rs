@@ -0,0 +1,55 @@
+const EXPOSURE_WARNING: f32 = 0.4;
+
+#[turbo::serialize]
+pub struct ExposureSettings {
+    // Field strength above which exposure starts to build up
+    pub threshold: f32,
+    // Exposure gained per frame spent above the threshold
+    pub fill_rate: f32,
+    // Exposure lost per frame spent below the threshold
+    pub drain_rate: f32,
+}
+
+impl ExposureSettings {
+    pub fn new(threshold: f32, fill_rate: f32, drain_rate: f32) -> Self {
+        Self {
+            threshold,
+            fill_rate,
+            drain_rate,
+        }
+    }
+}
+
+#[turbo::serialize]
+pub struct Exposure {
+    // Ranges from 0 (safe) to 1 (lethal)
+    value: f32,
+}
+
+impl Exposure {
+    pub fn new() -> Self {
+        Self {
+            value: 0.,
+        }
+    }
+
+    pub fn update(&mut self, field_strength: f32, settings: &ExposureSettings) {
+        if field_strength > settings.threshold {
+            self.value = (self.value + settings.fill_rate).min(1.);
+        } else {
+            self.value = (self.value - settings.drain_rate).max(0.);
+        }
+    }
+
+    pub fn get_value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn is_warning(&self) -> bool {
+        self.value >= EXPOSURE_WARNING
+    }
+
+    pub fn is_lethal(&self) -> bool {
+        self.value >= 1.
+    }
+}
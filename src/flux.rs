@@ -27,12 +27,13 @@ impl FluxCore {
         }
     }
     
-    pub fn get_strength(&self) -> f32 {
-        self.amplitude * f32::cos(self.time_offset + self.period_s * (time::tick() as f32 / 60.) * PI * 2.)
+    // Driven by the level clock rather than the global tick, so client and server agree on it
+    pub fn get_strength(&self, frame: u32) -> f32 {
+        self.amplitude * f32::cos(self.time_offset + self.period_s * (frame as f32 / 60.) * PI * 2.)
     }
 }
 
-pub fn calculate_line_flux(start: &Vector2, end: &Vector2, segment_count: u32, flux_cores: &Vec<FluxCore>) -> Vector2 {
+pub fn calculate_line_flux(start: &Vector2, end: &Vector2, segment_count: u32, flux_cores: &Vec<FluxCore>, frame: u32) -> Vector2 {
     let segments = line_to_segments(start, end, segment_count);
     let mut net_flux = Vector2::zero();
     
@@ -41,7 +42,7 @@ pub fn calculate_line_flux(start: &Vector2, end: &Vector2, segment_count: u32, f
         let end_option = segments.get((i + 1) as usize);
         match (start_option, end_option) {
             (Some(start), Some(end)) => {
-                net_flux += calculate_line_segment_flux(start, end, flux_cores);
+                net_flux += calculate_line_segment_flux(start, end, flux_cores, frame);
             },
             (_, _) => {
                 log!("Calculate line flux: mismatched line segment count!");
@@ -52,23 +53,23 @@ pub fn calculate_line_flux(start: &Vector2, end: &Vector2, segment_count: u32, f
     net_flux
 }
 
-pub fn net_flux_field_at_point(point: &Vector2, flux_cores: &Vec<FluxCore>) -> Vector2 {
+pub fn net_flux_field_at_point(point: &Vector2, flux_cores: &Vec<FluxCore>, frame: u32) -> Vector2 {
     let mut total_flux = Vector2::zero();
     for flux_core in flux_cores  {
-        total_flux += flux_field_at_point(point, &flux_core)     
+        total_flux += flux_field_at_point(point, flux_core, frame)     
     }
     
     total_flux
 }
 
-fn flux_field_at_point(point: &Vector2, flux_core: &FluxCore) -> Vector2 {
+fn flux_field_at_point(point: &Vector2, flux_core: &FluxCore, frame: u32) -> Vector2 {
     let r = point - &flux_core.solid.position;
     match flux_core.core_type {
         FluxCoreType::Radial => {
-            r * (flux_core.get_strength() / (2. * PI * r.length_squared()))
+            r * (flux_core.get_strength(frame) / (2. * PI * r.length_squared()))
         },
         FluxCoreType::Rotational => {
-            r.rotate(PI / 2.) * (flux_core.get_strength() / (2. * PI * r.length_squared()))
+            r.rotate(PI / 2.) * (flux_core.get_strength(frame) / (2. * PI * r.length_squared()))
         },
     }
 }
@@ -86,11 +87,11 @@ pub fn line_to_segments(start: &Vector2, end: &Vector2, segment_count: u32) -> V
     segments
 }
 
-pub fn calculate_line_segment_flux(start: &Vector2, end: &Vector2, flux_cores: &Vec<FluxCore>) -> Vector2 {
+pub fn calculate_line_segment_flux(start: &Vector2, end: &Vector2, flux_cores: &Vec<FluxCore>, frame: u32) -> Vector2 {
     let mid_point = start + ((end - start) * 0.5);
     let delta = (end - start).length();
     
-    net_flux_field_at_point(&mid_point, flux_cores) * delta
+    net_flux_field_at_point(&mid_point, flux_cores, frame) * delta
 }
 
 pub fn get_flux_line(rotation: f32, bounding_box: &BoundingBox) -> (Vector2, Vector2) {
@@ -0,0 +1,162 @@
+use crate::*;
+
+const MAX_SHAKE_OFFSET: f32 = 8.;
+const TRAUMA_DECAY: f32 = 0.03;
+
+#[turbo::serialize]
+pub struct CameraSettings {
+    // The focus only moves once the target leaves this box
+    pub deadzone_width: f32,
+    // Vertical deadzone used while airborne, on the ground the camera snaps to the player
+    pub deadzone_height: f32,
+    pub look_ahead_distance: f32,
+    pub look_ahead_speed: f32,
+    pub follow_speed: f32,
+}
+
+impl CameraSettings {
+    pub fn new() -> Self {
+        Self {
+            deadzone_width: 48.,
+            deadzone_height: 96.,
+            look_ahead_distance: 48.,
+            look_ahead_speed: 0.05,
+            follow_speed: 0.12,
+        }
+    }
+}
+
+#[turbo::serialize]
+pub enum CameraZoneMode {
+    // Holds the camera still on the given center, e.g. for set-piece rooms
+    Lock(Vector2),
+    // Shifts the framing by the given offset
+    Bias(Vector2),
+}
+
+#[turbo::serialize]
+pub struct CameraZone {
+    pub solid: Solid,
+    pub mode: CameraZoneMode,
+}
+
+impl CameraZone {
+    pub fn new(position_x: f32, position_y: f32, width: f32, height: f32, mode: CameraZoneMode) -> Self {
+        Self {
+            solid: Solid {
+                position: Vector2 { x: position_x, y: position_y },
+                width,
+                height,
+            },
+            mode,
+        }
+    }
+}
+
+#[turbo::serialize]
+pub struct Camera {
+    pub settings: CameraSettings,
+    position: Vector2,
+    focus: Vector2,
+    look_ahead: f32,
+    look_direction: f32,
+    trauma: f32,
+}
+
+impl Camera {
+    pub fn new(position: Vector2) -> Self {
+        Self {
+            settings: CameraSettings::new(),
+            position,
+            focus: position,
+            look_ahead: 0.,
+            look_direction: 0.,
+            trauma: 0.,
+        }
+    }
+
+    // Tracks a player through the deadzone, look_direction is -1 for left and 1 for right
+    pub fn follow(&mut self, target: &Vector2, look_direction: f32, is_grounded: bool) {
+        let half_width = self.settings.deadzone_width / 2.;
+        if target.x > self.focus.x + half_width {
+            self.focus.x = target.x - half_width;
+        } else if target.x < self.focus.x - half_width {
+            self.focus.x = target.x + half_width;
+        }
+
+        if is_grounded {
+            self.focus.y = target.y;
+        } else {
+            let half_height = self.settings.deadzone_height / 2.;
+            self.focus.y = self.focus.y.clamp(target.y - half_height, target.y + half_height);
+        }
+
+        self.look_direction = look_direction;
+    }
+
+    // Skips the deadzone and look-ahead, used when the target already frames several players
+    pub fn follow_directly(&mut self, target: &Vector2) {
+        self.focus = *target;
+        self.look_direction = 0.;
+    }
+
+    pub fn update(&mut self, tilemap: &TileMap, zones: &[CameraZone]) {
+        let look_ahead_target = self.look_direction * self.settings.look_ahead_distance;
+        self.look_ahead = lerp(self.look_ahead, look_ahead_target, self.settings.look_ahead_speed);
+
+        let mut desired_position = self.focus + Vector2::new(self.look_ahead, 0.);
+        match zones.iter().find(|zone| zone.solid.get_bound().contains(self.focus)) {
+            Some(CameraZone { solid: _, mode: CameraZoneMode::Lock(center) }) => desired_position = *center,
+            Some(CameraZone { solid: _, mode: CameraZoneMode::Bias(offset) }) => desired_position += offset,
+            None => {},
+        }
+        let desired_position = tilemap.lock_viewport_to_tilemap(&desired_position, &Vector2::new(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32));
+        self.position = self.position.lerp(&desired_position, self.settings.follow_speed);
+
+        self.trauma = (self.trauma - TRAUMA_DECAY).max(0.);
+    }
+
+    // Shake grows with the square of trauma, so small hits stay subtle and big ones stack up
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.);
+    }
+
+    pub fn apply(&self) {
+        let shake = self.trauma * self.trauma * MAX_SHAKE_OFFSET;
+        let offset_x = (random::f32() * 2. - 1.) * shake;
+        let offset_y = (random::f32() * 2. - 1.) * shake;
+        set_xy(self.position.x + offset_x, self.position.y + offset_y);
+    }
+
+    pub fn get_position(&self) -> Vector2 {
+        self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settle(camera: &mut Camera, level: &Level, target: &Vector2) {
+        for _ in 0..300 {
+            camera.follow(target, 1., true);
+            camera.update(&level.tilemap, &level.camera_zones);
+        }
+    }
+
+    #[test]
+    fn lock_zone_holds_the_camera_on_its_center() {
+        let level = construct_level_1();
+        let mut camera = Camera::new(Vector2::new(256., 144.));
+        settle(&mut camera, &level, &Vector2::new(300., 200.));
+        assert!((camera.get_position().x - 400.).abs() < 0.5);
+    }
+
+    #[test]
+    fn camera_follows_the_player_outside_of_zones() {
+        let level = construct_level_1();
+        let mut camera = Camera::new(Vector2::new(256., 144.));
+        settle(&mut camera, &level, &Vector2::new(600., 200.));
+        assert!(camera.get_position().x > 500.);
+    }
+}
@@ -0,0 +1,460 @@
+use crate::*;
+
+// Headless stand-in for turbo-os: runs FluxGameStateChannel and simulated clients in-process
+// and routes their messages through links with configurable latency, jitter, loss and reordering.
+// Only built with the `harness` feature.
+
+// Extra delay for packets picked for reordering, enough to land behind the next couple of packets
+const REORDER_DELAY_MS: f64 = CLIENT_FRAME_MS as f64 * 3.;
+
+#[derive(Clone, Copy)]
+pub struct NetworkConditions {
+    // One way, so the round trip is twice this
+    pub latency_ms: u32,
+    // Random extra delay on top of the latency, up to this much
+    pub jitter_ms: u32,
+    // Chance from 0 to 1 for a packet to be dropped
+    pub loss: f32,
+    // Chance from 0 to 1 for a packet to arrive after packets sent later
+    pub reorder: f32,
+}
+
+impl NetworkConditions {
+    pub fn ideal() -> Self {
+        Self {
+            latency_ms: 0,
+            jitter_ms: 0,
+            loss: 0.,
+            reorder: 0.,
+        }
+    }
+
+    pub fn with_rtt(rtt_ms: u32) -> Self {
+        Self {
+            latency_ms: rtt_ms / 2,
+            ..Self::ideal()
+        }
+    }
+}
+
+// Xorshift, so a failing scenario plays out the same way every run
+struct HarnessRng {
+    state: u32,
+}
+
+impl HarnessRng {
+    fn new(seed: u32) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state as f32 / u32::MAX as f32
+    }
+}
+
+// One direction of a connection
+struct LinkSimulator<T> {
+    conditions: NetworkConditions,
+    rng: HarnessRng,
+    // (delivery time, send order, payload)
+    in_flight: Vec<(f64, u64, T)>,
+    next_sequence: u64,
+}
+
+impl<T> LinkSimulator<T> {
+    fn new(conditions: NetworkConditions, seed: u32) -> Self {
+        Self {
+            conditions,
+            rng: HarnessRng::new(seed),
+            in_flight: vec![],
+            next_sequence: 0,
+        }
+    }
+
+    fn send(&mut self, now_ms: f64, payload: T) {
+        if self.rng.next_f32() < self.conditions.loss {
+            return;
+        }
+        let mut delay_ms = self.conditions.latency_ms as f64 + self.rng.next_f32() as f64 * self.conditions.jitter_ms as f64;
+        if self.rng.next_f32() < self.conditions.reorder {
+            delay_ms += REORDER_DELAY_MS;
+        }
+        self.in_flight.push((now_ms + delay_ms, self.next_sequence, payload));
+        self.next_sequence += 1;
+    }
+
+    // Everything due by now, in arrival order
+    fn receive(&mut self, now_ms: f64) -> Vec<T> {
+        self.in_flight.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        let due_count = self.in_flight.iter().take_while(|(deliver_at_ms, _, _)| *deliver_at_ms <= now_ms).count();
+        self.in_flight.drain(..due_count).map(|(_, _, payload)| payload).collect()
+    }
+}
+
+// Buttons a scenario holds down on a given frame
+#[derive(Clone, Copy, Default)]
+pub struct HarnessInput {
+    pub left: bool,
+    pub right: bool,
+    pub jump: bool,
+    pub dash: bool,
+    pub pick: bool,
+}
+
+impl HarnessInput {
+    fn to_user_input(self, tick: usize, previous: &HarnessInput) -> UserInput {
+        UserInput {
+            tick,
+            jump_pressed: self.jump,
+            jump_just_pressed: self.jump && !previous.jump,
+            left_pressed: self.left,
+            right_pressed: self.right,
+            dash_just_pressed: self.dash && !previous.dash,
+            pick_just_pressed: self.pick && !previous.pick,
+            ..UserInput::new()
+        }
+    }
+}
+
+// Client side of co-op without rendering: predicts, sends inputs and reconciles like GameState does
+pub struct SimulatedClient {
+    pub user_id: String,
+    pub local_player: Player,
+    pub level: Level,
+    prediction_buffer: PredictionBuffer,
+    snapshot_history: SnapshotHistory,
+    tick: usize,
+    previous_input: HarnessInput,
+    is_started: bool,
+    // Every comparison with a server checksum, in arrival order
+    pub checksum_checks: Vec<ChecksumCheck>,
+    uplink: LinkSimulator<ClientMsg>,
+    downlink: LinkSimulator<ServerMsg>,
+}
+
+impl SimulatedClient {
+    fn new(user_id: String, conditions: NetworkConditions, seed: u32) -> Self {
+        let level = LevelManager::new().loaded_level;
+        Self {
+            user_id,
+            local_player: Player::new(0., 0.),
+            level,
+            prediction_buffer: PredictionBuffer::new(PREDICTION_BUFFER_SIZE),
+            snapshot_history: SnapshotHistory::new(),
+            tick: 0,
+            previous_input: HarnessInput::default(),
+            is_started: false,
+            checksum_checks: vec![],
+            uplink: LinkSimulator::new(conditions, seed),
+            downlink: LinkSimulator::new(conditions, seed.wrapping_mul(31)),
+        }
+    }
+
+    fn handle_message(&mut self, msg: ServerMsg) {
+        match msg {
+            ServerMsg::StartGame => self.is_started = true,
+            ServerMsg::GameState { snapshot, last_processed_tick, checksum } => {
+                let WorldSnapshot { server_tick: _, level_frame, mut players, harvesters, actor_manager } = match self.snapshot_history.decode(snapshot) {
+                    Some(snapshot) => snapshot,
+                    None => return,
+                };
+                let server_local_player = match players.remove(&self.user_id) {
+                    Some(player) => player,
+                    None => return,
+                };
+                // The first snapshot places the player, before that there is nothing to predict from
+                if self.local_player.id.is_empty() {
+                    self.local_player = server_local_player.clone();
+                }
+                let checksum_check = reconcile_local_state(
+                    &mut self.local_player,
+                    &mut self.level,
+                    &mut self.prediction_buffer,
+                    last_processed_tick,
+                    ServerState { level_frame, player: server_local_player, harvesters, actor_manager, checksum },
+                );
+                self.checksum_checks.extend(checksum_check);
+            },
+            // Level changes, deaths and disconnects are outside what the harness covers
+            _ => {},
+        }
+    }
+
+    fn step(&mut self, now_ms: f64, input: HarnessInput) {
+        for msg in self.downlink.receive(now_ms) {
+            self.handle_message(msg);
+        }
+        if !self.is_started || self.local_player.id.is_empty() {
+            return;
+        }
+
+        self.tick += 1;
+        let user_input = input.to_user_input(self.tick, &self.previous_input);
+        self.previous_input = input;
+        let mut user_inputs = self.prediction_buffer.get_recent_inputs(REDUNDANT_INPUT_COUNT - 1);
+        user_inputs.push(user_input.clone());
+        self.uplink.send(now_ms, ClientMsg::UserInput {
+            user_inputs,
+            acknowledged_snapshot: self.snapshot_history.get_latest_tick(),
+        });
+
+        simulate_frame(&mut self.local_player, &mut self.level, &user_input);
+        self.prediction_buffer.record(&user_input, &self.local_player, &self.level);
+    }
+}
+
+pub struct ChannelHarness {
+    pub server: FluxGameStateChannel,
+    pub clients: Vec<SimulatedClient>,
+    conditions: NetworkConditions,
+    now_ms: f64,
+    next_interval_ms: f64,
+}
+
+impl ChannelHarness {
+    // Connects the clients and readies them, co-op needs at least two for the game to start
+    pub fn new(client_count: usize, conditions: NetworkConditions) -> Self {
+        start_capturing_messages();
+        let mut server = FluxGameStateChannel::new();
+        let _ = server.on_open(&mut ChannelSettings::default());
+
+        let mut harness = Self {
+            server,
+            clients: vec![],
+            conditions,
+            now_ms: 0.,
+            next_interval_ms: SERVER_TICK_MS as f64,
+        };
+        for idx in 0..client_count {
+            let _ = harness.add_client(&format!("client-{}", idx));
+        }
+        harness
+    }
+
+    // Connects one more client, which readies up as soon as its message arrives
+    pub fn add_client(&mut self, user_id: &str) -> Result<(), std::io::Error> {
+        self.server.on_connect(user_id)?;
+        let mut client = SimulatedClient::new(user_id.to_string(), self.conditions, 0x9e37_79b9 ^ self.clients.len() as u32);
+        client.uplink.send(self.now_ms, ClientMsg::Ready);
+        self.clients.push(client);
+        self.route_server_messages();
+        Result::Ok(())
+    }
+
+    // Drops a client's connection for good, the server still holds its slot for the grace period
+    pub fn remove_client(&mut self, client_idx: usize) -> SimulatedClient {
+        let client = self.clients.remove(client_idx);
+        let _ = self.server.on_disconnect(&client.user_id);
+        self.route_server_messages();
+        client
+    }
+
+    fn route_server_messages(&mut self) {
+        for (recipient, bytes) in take_captured_messages() {
+            let msg: ServerMsg = match borsh::from_slice(&bytes) {
+                Ok(msg) => msg,
+                Err(_) => continue,
+            };
+            for client in &mut self.clients {
+                if recipient.as_ref().is_none_or(|user_id| *user_id == client.user_id) {
+                    client.downlink.send(self.now_ms, msg.clone());
+                }
+            }
+        }
+    }
+
+    // Advances everyone by one client frame, input_for gets the client index
+    pub fn step(&mut self, mut input_for: impl FnMut(usize) -> HarnessInput) {
+        self.now_ms += CLIENT_FRAME_MS as f64;
+
+        for (idx, client) in self.clients.iter_mut().enumerate() {
+            client.step(self.now_ms, input_for(idx));
+        }
+
+        for client in &mut self.clients {
+            for msg in client.uplink.receive(self.now_ms) {
+                let _ = self.server.on_data(&client.user_id, msg);
+            }
+        }
+        while self.next_interval_ms <= self.now_ms {
+            let _ = self.server.on_interval();
+            self.next_interval_ms += SERVER_TICK_MS as f64;
+        }
+        self.route_server_messages();
+    }
+
+    pub fn run(&mut self, frame_count: u32, mut input_for: impl FnMut(usize) -> HarnessInput) {
+        for _ in 0..frame_count {
+            self.step(&mut input_for);
+        }
+    }
+
+    pub fn get_server_player(&self, client_idx: usize) -> Option<&Player> {
+        let client = self.clients.get(client_idx)?;
+        self.server.players.get(&client.user_id).map(|slot| &slot.player)
+    }
+
+    // Distance between where the client predicts itself and where the server has it
+    pub fn get_position_error(&self, client_idx: usize) -> Option<f32> {
+        let server_player = self.get_server_player(client_idx)?;
+        let client_player = &self.clients.get(client_idx)?.local_player;
+        Some((client_player.get_position() - server_player.get_position()).length())
+    }
+}
+
+// The "predicted position equals server position" check: the first client walks right for
+// move_frames, then everyone idles for settle_frames. Returns the remaining position error.
+pub fn measure_convergence(conditions: NetworkConditions, move_frames: u32, settle_frames: u32) -> Option<f32> {
+    let mut harness = ChannelHarness::new(2, conditions);
+    // Let the start message and the first snapshots arrive
+    harness.run(30, |_| HarnessInput::default());
+    harness.run(move_frames, |idx| HarnessInput { right: idx == 0, ..HarnessInput::default() });
+    harness.run(settle_frames, |_| HarnessInput::default());
+    harness.get_position_error(0)
+}
+
+// The replay check: plays a scripted run while recording it, round-trips the recording through
+// the save format and plays it back. True when playback ends on the same position and total flux.
+pub fn verify_replay(level_name: LevelName, frame_count: u32, mut input_for: impl FnMut(u32) -> HarnessInput) -> bool {
+    let (mut player, mut level_manager) = load_replay_level(&level_name);
+    let mut recording = Recording::new(level_name, level_manager.loaded_level.movement_profile.name.clone());
+    let mut previous_input = HarnessInput::default();
+    for frame in 0..frame_count {
+        let input = input_for(frame);
+        let user_input = input.to_user_input(frame as usize, &previous_input);
+        previous_input = input;
+        simulate_single_player(&mut player, &mut level_manager, &user_input);
+        recording.push(&user_input);
+    }
+    recording.outcome = Some(ReplayOutcome::capture(&player, &level_manager.loaded_level));
+
+    let recording: Recording = match borsh::to_vec(&recording).and_then(|bytes| borsh::from_slice(&bytes)) {
+        Ok(recording) => recording,
+        Err(_) => return false,
+    };
+    let replayed_outcome = play_recording_headless(&recording);
+    recording.outcome.is_some_and(|outcome| outcome.matches(&replayed_outcome))
+}
+
+#[cfg(all(test, feature = "harness"))]
+mod tests {
+    use super::*;
+
+    // Below a pixel the predicted and the server position count as the same
+    const MAX_CONVERGED_ERROR: f32 = 0.5;
+
+    fn assert_converges(conditions: NetworkConditions) {
+        let error = measure_convergence(conditions, 60, 120).expect("the client never got placed by a snapshot");
+        assert!(error < MAX_CONVERGED_ERROR, "prediction is {} px off the server", error);
+    }
+
+    #[test]
+    fn prediction_converges_on_an_ideal_network() {
+        assert_converges(NetworkConditions::ideal());
+    }
+
+    #[test]
+    fn prediction_converges_under_latency() {
+        assert_converges(NetworkConditions::with_rtt(200));
+    }
+
+    #[test]
+    fn prediction_converges_under_loss() {
+        assert_converges(NetworkConditions {
+            latency_ms: 50,
+            jitter_ms: 20,
+            loss: 0.1,
+            reorder: 0.05,
+        });
+    }
+
+    fn assert_no_desync(conditions: NetworkConditions) {
+        let mut harness = ChannelHarness::new(2, conditions);
+        let mut frame = 0;
+        // Both players move, jump and push the level around for several checksum intervals
+        harness.run(600, |idx| {
+            frame += 1;
+            HarnessInput {
+                right: idx == 0 && (frame / 100) % 2 == 0,
+                left: idx == 1 && (frame / 50) % 3 == 0,
+                jump: (frame / 30) % 4 == 0,
+                ..HarnessInput::default()
+            }
+        });
+        for client in &harness.clients {
+            assert!(client.checksum_checks.contains(&ChecksumCheck::Matched), "no checksum was ever compared");
+            let desyncs: Vec<&ChecksumCheck> = client.checksum_checks.iter().filter(|check| **check != ChecksumCheck::Matched).collect();
+            assert!(desyncs.is_empty(), "{:?}", desyncs);
+        }
+    }
+
+    #[test]
+    fn ideal_network_reports_no_desync() {
+        assert_no_desync(NetworkConditions::ideal());
+    }
+
+    #[test]
+    fn latency_and_loss_report_no_desync() {
+        assert_no_desync(NetworkConditions {
+            latency_ms: 40,
+            jitter_ms: 30,
+            loss: 0.05,
+            reorder: 0.05,
+        });
+    }
+
+    #[test]
+    fn partner_picking_up_a_harvester_is_no_desync() {
+        let mut harness = ChannelHarness::new(2, NetworkConditions::with_rtt(60));
+        harness.run(30, |_| HarnessInput::default());
+        // The partner walks to the harvester next to its start and carries it back, while the
+        // first client keeps moving so its predictions get checked all along
+        for frame in 0..600 {
+            harness.run(1, |idx| match idx {
+                0 => HarnessInput { right: (frame / 60) % 2 == 0, left: (frame / 60) % 2 == 1, jump: frame % 50 < 10, ..HarnessInput::default() },
+                _ if frame < 30 => HarnessInput { left: true, ..HarnessInput::default() },
+                _ if frame < 60 => HarnessInput { pick: frame < 50, ..HarnessInput::default() },
+                _ => HarnessInput { right: frame < 200, ..HarnessInput::default() },
+            });
+        }
+        assert!(harness.get_server_player(1).unwrap().get_picked_item().is_some(), "the partner never picked up the harvester");
+        let client = &harness.clients[0];
+        assert!(client.checksum_checks.contains(&ChecksumCheck::Matched), "no checksum was ever compared");
+        assert!(client.checksum_checks.iter().all(|check| *check == ChecksumCheck::Matched), "{:?}", client.checksum_checks);
+    }
+
+    #[test]
+    fn slot_freed_after_the_grace_period_goes_to_a_new_player() {
+        let mut harness = ChannelHarness::new(2, NetworkConditions::with_rtt(60));
+        harness.run(30, |_| HarnessInput::default());
+        harness.remove_client(1);
+        let grace_frames = (RECONNECT_GRACE_TICKS * SERVER_TICK_MS) as f32 / CLIENT_FRAME_MS;
+        harness.run(grace_frames as u32 + 30, |_| HarnessInput::default());
+
+        // Alone the survivor is back in the lobby and has to ready up again
+        assert!(!harness.server.game_started);
+        assert!(harness.server.players.values().all(|slot| !slot.is_ready));
+        harness.add_client("client-2").expect("the freed slot was not offered to a new player");
+        harness.clients[0].uplink.send(harness.now_ms, ClientMsg::Ready);
+        harness.run(30, |_| HarnessInput::default());
+        assert!(harness.server.game_started);
+
+        let start_x = harness.get_server_player(1).unwrap().get_position().x;
+        harness.run(60, |idx| HarnessInput { left: idx == 1, ..HarnessInput::default() });
+        assert!(harness.get_server_player(1).unwrap().get_position().x < start_x - 16.);
+    }
+
+    #[test]
+    fn walking_client_moves_on_the_server() {
+        let mut harness = ChannelHarness::new(2, NetworkConditions::with_rtt(100));
+        harness.run(30, |_| HarnessInput::default());
+        let start_x = harness.get_server_player(0).unwrap().get_position().x;
+        harness.run(60, |idx| HarnessInput { right: idx == 0, ..HarnessInput::default() });
+        harness.run(60, |_| HarnessInput::default());
+        assert!(harness.get_server_player(0).unwrap().get_position().x > start_x + 16.);
+        assert!(harness.get_position_error(0).unwrap() < MAX_CONVERGED_ERROR);
+    }
+}
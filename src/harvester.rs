@@ -59,14 +59,14 @@ impl Harvester {
         }
     }
     
-    pub fn calculate_flux(&mut self, actor_manager: &mut ActorManager, flux_cores: &Vec<FluxCore>) -> f32 {
+    pub fn calculate_flux(&mut self, actor_manager: &mut ActorManager, flux_cores: &Vec<FluxCore>, frame: u32) -> f32 {
         let actor = actor_manager.get_actor(self.actor);
         match actor {
             Some(actor) => {
                 let bounding_box = actor.get_bound();
                 let (start, end) = get_flux_line(self.rotation, &bounding_box);
                 
-                self.flux_field = calculate_line_flux(&start, &end, 6, flux_cores);
+                self.flux_field = calculate_line_flux(&start, &end, 6, flux_cores, frame);
                 self.flux = (end - start).get_normal_vector().normalize().dot(&self.flux_field);
                 //(end - start).get_normal_vector().normalize().draw_at_point(&actor.position, self.flux / 10.);
                 self.flux
@@ -75,6 +75,10 @@ impl Harvester {
         }
     }
     
+    pub fn get_flux(&self) -> f32 {
+        self.flux
+    }
+
     pub fn draw(&self, actor_manager: &ActorManager) {
         let actor_option = actor_manager.get_actor(self.actor);
         match actor_option {
@@ -2,11 +2,13 @@ use crate::*;
 
 const SHAKE_COUNT: u32 = 15;
 const SHAKE_INTENSITY: i32 = 2;
+const EXPOSURE_BAR_WIDTH: f32 = 231.;
 
 #[turbo::serialize]
 pub struct Hud {
     total_flux: f32,
     required_flux: f32,
+    exposure: f32,
     shake_counter_tick: u32,
     shake_counter_x_offset: i32,
     shake_counter_y_offset: i32,
@@ -17,18 +19,20 @@ impl Hud {
         Hud {
             required_flux: 0.,
             total_flux: 0.,
+            exposure: 0.,
             shake_counter_tick: 0,
             shake_counter_x_offset: 0,
             shake_counter_y_offset: 0,
         }
     }
     
-    pub fn update(&mut self, total_flux: f32, required_flux: f32) {
+    pub fn update(&mut self, total_flux: f32, required_flux: f32, exposure: f32) {
         if (self.total_flux / FLUX_PER_UNIT) as i32 != (total_flux / FLUX_PER_UNIT) as i32 {
             self.shake_counter_tick = SHAKE_COUNT;
         } 
         self.total_flux = total_flux;
         self.required_flux = required_flux;
+        self.exposure = exposure;
 
         if self.shake_counter_tick > 0 {
             self.shake_counter_x_offset = random::i32() % SHAKE_INTENSITY;
@@ -91,5 +95,39 @@ impl Hud {
             font = "large",
             fixed = true,
         );
+
+        self.draw_exposure_meter();
+    }
+
+    fn draw_exposure_meter(&self) {
+        if self.exposure <= 0. {
+            return;
+        }
+
+        rect!(
+            w = EXPOSURE_BAR_WIDTH as u32,
+            h = 3,
+            x = 144,
+            y = 26,
+            color = 0x630f75ff,
+            border_radius = 1,
+            fixed = true,
+        );
+
+        // Blink the meter once the exposure gets dangerous
+        let color = if self.exposure >= 0.75 && (time::tick() / 6).is_multiple_of(2) {
+            0xffffffff
+        } else {
+            0xff3b3bff
+        };
+        rect!(
+            w = (EXPOSURE_BAR_WIDTH * self.exposure) as u32,
+            h = 3,
+            x = 144,
+            y = 26,
+            color = color,
+            border_radius = 1,
+            fixed = true,
+        );
     }
 }
\ No newline at end of file
@@ -0,0 +1,387 @@
+use crate::*;
+
+#[turbo::serialize]
+#[derive(PartialEq, Copy)]
+pub enum Action {
+    Left,
+    Right,
+    Up,
+    Down,
+    Jump,
+    Pick,
+    Dash,
+    Confirm,
+    Back,
+    DebugMenu,
+    Rewind,
+    // Leaves a time attack or a replay, on buttons gameplay doesn't use
+    Quit,
+}
+
+impl Action {
+    // Rebinding these never removes their defaults, so the menus stay reachable and a run can always be left
+    pub fn is_menu_navigation(&self) -> bool {
+        matches!(self, Action::Up | Action::Down | Action::Confirm | Action::Back | Action::Quit)
+    }
+
+    pub fn all() -> Vec<Action> {
+        vec![
+            Action::Left,
+            Action::Right,
+            Action::Up,
+            Action::Down,
+            Action::Jump,
+            Action::Pick,
+            Action::Dash,
+            Action::Confirm,
+            Action::Back,
+            Action::DebugMenu,
+            Action::Rewind,
+            Action::Quit,
+        ]
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action_str = match self {
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Jump => "Jump",
+            Action::Pick => "Pick",
+            Action::Dash => "Dash",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::DebugMenu => "Debug Menu",
+            Action::Rewind => "Rewind",
+            Action::Quit => "Quit",
+        };
+        write!(f, "{}", action_str)
+    }
+}
+
+#[turbo::serialize]
+#[derive(PartialEq, Copy)]
+pub enum GamepadButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    B,
+    X,
+    Y,
+    Start,
+    Select,
+}
+
+impl GamepadButton {
+    fn all() -> Vec<GamepadButton> {
+        vec![
+            GamepadButton::Up,
+            GamepadButton::Down,
+            GamepadButton::Left,
+            GamepadButton::Right,
+            GamepadButton::A,
+            GamepadButton::B,
+            GamepadButton::X,
+            GamepadButton::Y,
+            GamepadButton::Start,
+            GamepadButton::Select,
+        ]
+    }
+
+    fn get_state(&self, gamepad: &gamepad::Gamepad) -> ButtonState {
+        let button = match self {
+            GamepadButton::Up => gamepad.up,
+            GamepadButton::Down => gamepad.down,
+            GamepadButton::Left => gamepad.left,
+            GamepadButton::Right => gamepad.right,
+            GamepadButton::A => gamepad.a,
+            GamepadButton::B => gamepad.b,
+            GamepadButton::X => gamepad.x,
+            GamepadButton::Y => gamepad.y,
+            GamepadButton::Start => gamepad.start,
+            GamepadButton::Select => gamepad.select,
+        };
+        ButtonState { pressed: button.pressed(), just_pressed: button.just_pressed() }
+    }
+}
+
+// Keys that can be bound to actions. Turbo's key codes can't be serialized into the settings
+#[turbo::serialize]
+#[derive(PartialEq, Copy)]
+pub enum Key {
+    ArrowUp, ArrowDown, ArrowLeft, ArrowRight,
+    Space, Enter, Escape, Tab, ShiftLeft, ShiftRight,
+    A, B, C, D, E, F, G, H, I, J, K, L, M,
+    N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+}
+
+impl Key {
+    fn all() -> Vec<Key> {
+        vec![
+            Key::ArrowUp, Key::ArrowDown, Key::ArrowLeft, Key::ArrowRight,
+            Key::Space, Key::Enter, Key::Escape, Key::Tab, Key::ShiftLeft, Key::ShiftRight,
+            Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+            Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+        ]
+    }
+
+    fn get_state(&self, keyboard: &keyboard::Keyboard) -> ButtonState {
+        let key = match self {
+            Key::ArrowUp => keyboard.arrow_up(),
+            Key::ArrowDown => keyboard.arrow_down(),
+            Key::ArrowLeft => keyboard.arrow_left(),
+            Key::ArrowRight => keyboard.arrow_right(),
+            Key::Space => keyboard.space(),
+            Key::Enter => keyboard.enter(),
+            Key::Escape => keyboard.escape(),
+            Key::Tab => keyboard.tab(),
+            Key::ShiftLeft => keyboard.shift_left(),
+            Key::ShiftRight => keyboard.shift_right(),
+            Key::A => keyboard.key_a(),
+            Key::B => keyboard.key_b(),
+            Key::C => keyboard.key_c(),
+            Key::D => keyboard.key_d(),
+            Key::E => keyboard.key_e(),
+            Key::F => keyboard.key_f(),
+            Key::G => keyboard.key_g(),
+            Key::H => keyboard.key_h(),
+            Key::I => keyboard.key_i(),
+            Key::J => keyboard.key_j(),
+            Key::K => keyboard.key_k(),
+            Key::L => keyboard.key_l(),
+            Key::M => keyboard.key_m(),
+            Key::N => keyboard.key_n(),
+            Key::O => keyboard.key_o(),
+            Key::P => keyboard.key_p(),
+            Key::Q => keyboard.key_q(),
+            Key::R => keyboard.key_r(),
+            Key::S => keyboard.key_s(),
+            Key::T => keyboard.key_t(),
+            Key::U => keyboard.key_u(),
+            Key::V => keyboard.key_v(),
+            Key::W => keyboard.key_w(),
+            Key::X => keyboard.key_x(),
+            Key::Y => keyboard.key_y(),
+            Key::Z => keyboard.key_z(),
+        };
+        ButtonState { pressed: key.pressed(), just_pressed: key.just_pressed() }
+    }
+}
+
+struct ButtonState {
+    pressed: bool,
+    just_pressed: bool,
+}
+
+#[turbo::serialize]
+#[derive(PartialEq, Copy)]
+pub enum InputSource {
+    Key(Key),
+    Button(GamepadButton),
+}
+
+impl InputSource {
+    fn is_same_kind(&self, other: &InputSource) -> bool {
+        matches!(
+            (self, other),
+            (InputSource::Key(_), InputSource::Key(_)) | (InputSource::Button(_), InputSource::Button(_))
+        )
+    }
+}
+
+impl fmt::Display for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputSource::Key(key) => write!(f, "{:?}", key),
+            InputSource::Button(button) => write!(f, "Pad {:?}", button),
+        }
+    }
+}
+
+#[turbo::serialize]
+pub struct Binding {
+    pub action: Action,
+    pub sources: Vec<InputSource>,
+}
+
+pub fn get_default_bindings() -> Vec<Binding> {
+    use GamepadButton as Pad;
+    let binding = |action: Action, sources: Vec<InputSource>| Binding { action, sources };
+    vec![
+        binding(Action::Left, vec![InputSource::Button(Pad::Left), InputSource::Key(Key::ArrowLeft), InputSource::Key(Key::A)]),
+        binding(Action::Right, vec![InputSource::Button(Pad::Right), InputSource::Key(Key::ArrowRight), InputSource::Key(Key::D)]),
+        binding(Action::Up, vec![InputSource::Button(Pad::Up), InputSource::Key(Key::ArrowUp), InputSource::Key(Key::W)]),
+        binding(Action::Down, vec![InputSource::Button(Pad::Down), InputSource::Key(Key::ArrowDown), InputSource::Key(Key::S)]),
+        binding(Action::Jump, vec![InputSource::Button(Pad::B), InputSource::Key(Key::Space)]),
+        binding(Action::Pick, vec![InputSource::Button(Pad::A), InputSource::Key(Key::E)]),
+        binding(Action::Dash, vec![InputSource::Button(Pad::Y), InputSource::Key(Key::ShiftLeft)]),
+        binding(Action::Confirm, vec![InputSource::Button(Pad::B), InputSource::Key(Key::Enter), InputSource::Key(Key::Space)]),
+        binding(Action::Back, vec![InputSource::Button(Pad::A), InputSource::Key(Key::Escape)]),
+        binding(Action::DebugMenu, vec![InputSource::Button(Pad::Select), InputSource::Key(Key::Tab)]),
+        binding(Action::Rewind, vec![InputSource::Button(Pad::X), InputSource::Key(Key::R)]),
+        binding(Action::Quit, vec![InputSource::Button(Pad::Start), InputSource::Key(Key::Escape)]),
+    ]
+}
+
+// Defaults for a player without the keyboard
+pub fn get_default_gamepad_bindings() -> Vec<Binding> {
+    get_default_bindings().into_iter()
+        .map(|binding| Binding {
+            action: binding.action,
+            sources: binding.sources.into_iter().filter(|source| matches!(source, InputSource::Button(_))).collect(),
+        })
+        .collect()
+}
+
+// Maps the physical inputs of one player to game actions
+#[turbo::serialize]
+pub struct InputMap {
+    pub gamepad_index: usize,
+    // Only one local player can own the keyboard
+    pub use_keyboard: bool,
+    pub bindings: Vec<Binding>,
+}
+
+impl InputMap {
+    pub fn new(gamepad_index: usize, use_keyboard: bool, bindings: Vec<Binding>) -> Self {
+        Self {
+            gamepad_index,
+            use_keyboard,
+            bindings,
+        }
+    }
+
+    fn get_source_states(&self, action: Action) -> Vec<ButtonState> {
+        let sources = match self.bindings.iter().find(|binding| binding.action == action) {
+            Some(binding) => &binding.sources,
+            None => return vec![],
+        };
+
+        let gamepad = gamepad::get(self.gamepad_index);
+        let keyboard = if self.use_keyboard { Some(keyboard::get()) } else { None };
+        sources.iter().filter_map(|source| match (source, &keyboard) {
+            (InputSource::Button(button), _) => Some(button.get_state(&gamepad)),
+            (InputSource::Key(key), Some(keyboard)) => Some(key.get_state(keyboard)),
+            (InputSource::Key(_), None) => None,
+        }).collect()
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.get_source_states(action).iter().any(|state| state.pressed)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.get_source_states(action).iter().any(|state| state.just_pressed)
+    }
+
+    pub fn any_just_pressed(&self) -> bool {
+        Action::all().into_iter().any(|action| self.just_pressed(action))
+    }
+
+    pub fn get_user_input(&self, tick: usize) -> UserInput {
+        UserInput {
+            tick,
+            jump_just_pressed: self.just_pressed(Action::Jump),
+            jump_pressed: self.pressed(Action::Jump),
+            left_pressed: self.pressed(Action::Left),
+            right_pressed: self.pressed(Action::Right),
+            up_pressed: self.pressed(Action::Up),
+            down_pressed: self.pressed(Action::Down),
+            pick_just_pressed: self.just_pressed(Action::Pick),
+            dash_just_pressed: self.just_pressed(Action::Dash),
+        }
+    }
+
+    pub fn get_sources(&self, action: Action) -> Vec<InputSource> {
+        match self.bindings.iter().find(|binding| binding.action == action) {
+            Some(binding) => binding.sources.clone(),
+            None => vec![],
+        }
+    }
+
+    pub fn get_default_bindings(&self) -> Vec<Binding> {
+        if self.use_keyboard { get_default_bindings() } else { get_default_gamepad_bindings() }
+    }
+
+    // Replaces the sources of the same kind, so rebinding a key keeps the gamepad binding and vice versa
+    pub fn rebind(&mut self, action: Action, source: InputSource) {
+        let default_sources = match self.get_default_bindings().into_iter().find(|binding| binding.action == action) {
+            Some(binding) if action.is_menu_navigation() => binding.sources,
+            _ => vec![],
+        };
+        match self.bindings.iter_mut().find(|binding| binding.action == action) {
+            Some(binding) => {
+                binding.sources.retain(|existing| !existing.is_same_kind(&source) || default_sources.contains(existing));
+                if !binding.sources.contains(&source) {
+                    binding.sources.push(source);
+                }
+            },
+            None => self.bindings.push(Binding { action, sources: vec![source] }),
+        }
+    }
+
+    // Returns the first input pressed this frame, used while waiting for a new binding
+    pub fn get_just_pressed_source(&self) -> Option<InputSource> {
+        let gamepad = gamepad::get(self.gamepad_index);
+        if let Some(button) = GamepadButton::all().into_iter().find(|button| button.get_state(&gamepad).just_pressed) {
+            return Some(InputSource::Button(button));
+        }
+
+        if self.use_keyboard {
+            let keyboard = keyboard::get();
+            if let Some(key) = Key::all().into_iter().find(|key| key.get_state(&keyboard).just_pressed) {
+                return Some(InputSource::Key(key));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_menu_navigation_keeps_the_defaults() {
+        let mut input_map = InputMap::new(0, true, get_default_bindings());
+        input_map.rebind(Action::Confirm, InputSource::Key(Key::K));
+        let sources = input_map.get_sources(Action::Confirm);
+        assert!(sources.contains(&InputSource::Key(Key::K)));
+        assert!(sources.contains(&InputSource::Key(Key::Enter)));
+        assert!(sources.contains(&InputSource::Button(GamepadButton::B)));
+    }
+
+    #[test]
+    fn rebinding_other_actions_replaces_the_same_kind() {
+        let mut input_map = InputMap::new(0, true, get_default_bindings());
+        input_map.rebind(Action::Jump, InputSource::Key(Key::K));
+        assert!(input_map.get_sources(Action::Jump) == vec![InputSource::Button(GamepadButton::B), InputSource::Key(Key::K)]);
+    }
+
+    #[test]
+    fn pick_does_not_quit_a_run() {
+        let gameplay_actions = [Action::Left, Action::Right, Action::Up, Action::Down, Action::Jump, Action::Pick, Action::Dash, Action::Rewind];
+        for input_map in [InputMap::new(0, true, get_default_bindings()), InputMap::new(1, false, get_default_gamepad_bindings())] {
+            let quit_sources = input_map.get_sources(Action::Quit);
+            assert!(!quit_sources.is_empty());
+            for action in gameplay_actions {
+                assert!(input_map.get_sources(action).iter().all(|source| !quit_sources.contains(source)), "{} also quits the run", action);
+            }
+        }
+    }
+
+    #[test]
+    fn players_keep_separate_bindings() {
+        let mut input_map2 = InputMap::new(1, false, get_default_gamepad_bindings());
+        input_map2.rebind(Action::Jump, InputSource::Button(GamepadButton::X));
+        let input_map = InputMap::new(0, true, get_default_bindings());
+        assert!(input_map.get_sources(Action::Jump).contains(&InputSource::Button(GamepadButton::B)));
+        assert!(input_map2.get_sources(Action::Jump) == vec![InputSource::Button(GamepadButton::X)]);
+    }
+}
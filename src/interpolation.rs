@@ -0,0 +1,153 @@
+use crate::*;
+
+// Remote players are rendered this far behind the newest server state
+const INTERPOLATION_DELAY_MS: f64 = 100.;
+const MAX_EXTRAPOLATION_MS: f64 = 60.;
+const CLOCK_OFFSET_SMOOTHING: f64 = 0.1;
+const MAX_SNAPSHOTS: usize = 32;
+
+#[turbo::serialize]
+pub struct RemoteSnapshot {
+    pub server_tick: u32,
+    pub player: Player,
+}
+
+impl RemoteSnapshot {
+    fn get_server_time_ms(&self) -> f64 {
+        self.server_tick as f64 * SERVER_TICK_MS as f64
+    }
+}
+
+#[turbo::serialize]
+pub struct SnapshotInterpolator {
+    // Ordered by server tick, oldest first
+    snapshots: VecDeque<RemoteSnapshot>,
+    // Estimated difference between the server clock and the local clock
+    clock_offset_ms: Option<f64>,
+    // Jitter in the offset estimate must not step the render time back, that flickers the animation
+    last_render_time_ms: Option<f64>,
+}
+
+impl SnapshotInterpolator {
+    pub fn new() -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            clock_offset_ms: None,
+            last_render_time_ms: None,
+        }
+    }
+
+    pub fn push(&mut self, server_tick: u32, player: Player, now_ms: u64) {
+        // Late packets are dropped, the interpolation already moved past them
+        if self.snapshots.back().is_some_and(|snapshot| snapshot.server_tick >= server_tick) {
+            return;
+        }
+
+        let snapshot = RemoteSnapshot { server_tick, player };
+        let offset_sample = snapshot.get_server_time_ms() - now_ms as f64;
+        self.clock_offset_ms = match self.clock_offset_ms {
+            Some(offset) => Some(offset + (offset_sample - offset) * CLOCK_OFFSET_SMOOTHING),
+            None => Some(offset_sample),
+        };
+
+        self.snapshots.push_back(snapshot);
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+    }
+
+    pub fn get_latest(&self) -> Option<&Player> {
+        self.snapshots.back().map(|snapshot| &snapshot.player)
+    }
+
+    // Remote player as it was INTERPOLATION_DELAY_MS ago in server time
+    pub fn sample(&mut self, now_ms: u64) -> Option<Player> {
+        let clock_offset_ms = self.clock_offset_ms?;
+        let render_time_ms = now_ms as f64 + clock_offset_ms - INTERPOLATION_DELAY_MS;
+        let render_time_ms = match self.last_render_time_ms {
+            Some(last_render_time_ms) => render_time_ms.max(last_render_time_ms),
+            None => render_time_ms,
+        };
+        self.last_render_time_ms = Some(render_time_ms);
+
+        // Only the newest snapshot at or before the render time is needed as the lower bracket
+        while self.snapshots.len() > 2 && self.snapshots[1].get_server_time_ms() <= render_time_ms {
+            self.snapshots.pop_front();
+        }
+
+        match (self.snapshots.front(), self.snapshots.get(1)) {
+            (Some(from), Some(to)) => {
+                let from_time_ms = from.get_server_time_ms();
+                let to_time_ms = to.get_server_time_ms();
+                if render_time_ms <= to_time_ms {
+                    let alpha = ((render_time_ms - from_time_ms) / (to_time_ms - from_time_ms)).clamp(0., 1.);
+                    Some(from.player.interpolate(&to.player, alpha as f32))
+                } else {
+                    // Ran out of snapshots, keep moving for a short while before holding still
+                    let extrapolation_ms = (render_time_ms - to_time_ms).min(MAX_EXTRAPOLATION_MS);
+                    let velocity = (to.player.get_position() - from.player.get_position()) * (1. / (to_time_ms - from_time_ms) as f32);
+                    let mut player = to.player.clone();
+                    player.actor.position = to.player.get_position() + velocity * extrapolation_ms as f32;
+                    Some(player)
+                }
+            },
+            (Some(only), None) => Some(only.player.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK_MS: u64 = SERVER_TICK_MS as u64;
+
+    // Snapshots of a player walking 10 px per tick, each arriving right when it's sent
+    fn walking_interpolator(ticks: u32) -> SnapshotInterpolator {
+        let mut interpolator = SnapshotInterpolator::new();
+        for tick in 0..ticks {
+            interpolator.push(tick, Player::new(tick as f32 * 10., 0.), tick as u64 * TICK_MS);
+        }
+        interpolator
+    }
+
+    #[test]
+    fn sample_interpolates_between_the_bracketing_snapshots() {
+        let mut interpolator = walking_interpolator(20);
+        let now_ms = 19 * TICK_MS;
+        let render_time_ms = now_ms as f64 - INTERPOLATION_DELAY_MS;
+        let expected_x = render_time_ms / TICK_MS as f64 * 10.;
+
+        let player = interpolator.sample(now_ms).unwrap();
+        assert!((player.get_position().x as f64 - expected_x).abs() < 0.01);
+
+        let half_tick_later = interpolator.sample(now_ms + TICK_MS / 2).unwrap();
+        assert!((half_tick_later.get_position().x as f64 - (expected_x + 5.)).abs() < 0.01);
+    }
+
+    #[test]
+    fn sample_extrapolates_no_further_than_the_cap() {
+        let mut interpolator = walking_interpolator(20);
+        let last_snapshot_x = 19. * 10.;
+        let max_extrapolation_x = MAX_EXTRAPOLATION_MS / TICK_MS as f64 * 10.;
+
+        // Long after the last snapshot the player holds still at the cap instead of drifting off
+        for now_ms in [19 * TICK_MS + 1000, 19 * TICK_MS + 5000] {
+            let player = interpolator.sample(now_ms).unwrap();
+            assert!((player.get_position().x as f64 - (last_snapshot_x + max_extrapolation_x)).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn late_snapshot_does_not_step_the_render_time_back() {
+        let mut interpolator = walking_interpolator(20);
+        let now_ms = 19 * TICK_MS;
+        let before = interpolator.sample(now_ms).unwrap().get_position().x;
+
+        // Arrives late, which drags the clock offset estimate back
+        interpolator.push(20, Player::new(200., 0.), 20 * TICK_MS + 400);
+        let after = interpolator.sample(now_ms).unwrap().get_position().x;
+        assert!(after >= before);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::*;
+
+const MIN_TARGET_DEPTH: usize = 1;
+const MAX_TARGET_DEPTH: usize = 8;
+// After this many frames without running dry the buffer tries to get by with one frame less
+const STABLE_FRAMES_BEFORE_SHRINK: u32 = 300;
+
+// Per-player input queue that hides packet jitter and loss from the simulation.
+// Inputs are keyed by tick so the redundant copies in every packet collapse into one,
+// and playback only starts once target_depth frames are queued.
+#[turbo::serialize]
+pub struct InputJitterBuffer {
+    inputs: BTreeMap<usize, UserInput>,
+    last_consumed_tick: Option<usize>,
+    // Repeated while the buffer is empty, so a late packet doesn't read as released buttons
+    held_input: UserInput,
+    target_depth: usize,
+    is_buffering: bool,
+    stable_frames: u32,
+}
+
+impl InputJitterBuffer {
+    pub fn new() -> Self {
+        Self {
+            inputs: BTreeMap::new(),
+            last_consumed_tick: None,
+            held_input: UserInput::new(),
+            target_depth: MIN_TARGET_DEPTH,
+            is_buffering: true,
+            stable_frames: 0,
+        }
+    }
+
+    pub fn push(&mut self, input: UserInput) {
+        if self.last_consumed_tick.is_some_and(|tick| input.tick <= tick) {
+            return;
+        }
+        self.inputs.entry(input.tick).or_insert(input);
+    }
+
+    // More queued than needed, the server can run an extra frame for this player to catch up
+    pub fn is_overfilled(&self) -> bool {
+        !self.is_buffering && self.inputs.len() > self.target_depth * 2
+    }
+
+    pub fn clear(&mut self) {
+        self.inputs.clear();
+        self.is_buffering = true;
+    }
+
+    // Input for the next simulated frame, None while the buffer refills.
+    // Ticks lost despite the redundancy are skipped rather than waited for.
+    pub fn pop(&mut self) -> Option<UserInput> {
+        if self.is_buffering {
+            if self.inputs.len() < self.target_depth {
+                return None;
+            }
+            self.is_buffering = false;
+        }
+
+        match self.inputs.pop_first() {
+            Some((tick, input)) => {
+                self.last_consumed_tick = Some(tick);
+                self.held_input = input.get_held();
+                self.stable_frames += 1;
+                if self.stable_frames >= STABLE_FRAMES_BEFORE_SHRINK {
+                    self.stable_frames = 0;
+                    self.target_depth = (self.target_depth - 1).max(MIN_TARGET_DEPTH);
+                }
+                Some(input)
+            },
+            None => {
+                // Ran dry, so the connection is worse than the buffer assumed
+                self.is_buffering = true;
+                self.stable_frames = 0;
+                self.target_depth = (self.target_depth + 1).min(MAX_TARGET_DEPTH);
+                None
+            },
+        }
+    }
+
+    pub fn get_held_input(&self) -> UserInput {
+        self.held_input.clone()
+    }
+}
@@ -1,5 +1,7 @@
 use crate::*;
 
+const JUICE_PARTICLE_SEED: u32 = 0x2545_f491;
+
 #[turbo::serialize]
 pub enum BurstSource {
     Point(f32, f32),
@@ -44,11 +46,16 @@ impl BurstConfig {}
 #[turbo::serialize]
 pub struct ParticleManager {
     pub bursts: Vec<Vec<Particle>>,
+    // Bursts are spawned from simulate_frame, so they can't use random::
+    rng: SimRng,
 }
 
 impl ParticleManager {
     pub fn new() -> Self {
-        Self { bursts: Vec::new() }
+        Self {
+            bursts: Vec::new(),
+            rng: SimRng::new(JUICE_PARTICLE_SEED),
+        }
     }
 
     pub fn create_burst(&mut self, config: &BurstConfig) {
@@ -60,7 +67,7 @@ impl ParticleManager {
     }
 
     // Helper function for generating random float values in a range
-    fn rand_float(&self, min: f32, max: f32) -> f32 {
+    fn rand_float(&mut self, min: f32, max: f32) -> f32 {
         let range = (max - min).abs();
         if range < 0.001 {
             return min;
@@ -72,18 +79,18 @@ impl ParticleManager {
             return min;
         }
 
-        min + (random::u32() % scaled_range) as f32 / 1000.0
+        min + (self.rng.next_u32() % scaled_range) as f32 / 1000.0
     }
 
     // Helper function for generating random integer values in a range
-    fn rand_int(&self, min: u32, max: u32) -> u32 {
+    fn rand_int(&mut self, min: u32, max: u32) -> u32 {
         if max <= min {
             return min;
         }
-        min + (random::u32() % (max - min))
+        min + (self.rng.next_u32() % (max - min))
     }
 
-    fn create_particle(&self, config: &BurstConfig) -> Particle {
+    fn create_particle(&mut self, config: &BurstConfig) -> Particle {
         // Get position based on source type
         let pos = match &config.source {
             BurstSource::Point(x, y) => (*x, *y),
@@ -5,9 +5,80 @@ pub struct Level {
     pub tilemap: TileMap,
     pub harvesters: Vec<Harvester>,
     pub actor_manager: ActorManager,
-    pub player1_start_position: Vector2,
-    pub player2_start_position: Vector2,
+    pub player_start_positions: Vec<Vector2>,
+    // Spawns of same-screen co-op, the network slots are spread too far apart for one screen
+    pub local_coop_start_positions: [Vector2; 2],
+    pub checkpoints: Vec<Checkpoint>,
+    pub checkpoint_snapshot: Option<CheckpointSnapshot>,
+    pub exits: Vec<ExitZone>,
     pub background: Background,
     pub required_flux: f32,
+    pub exposure_settings: ExposureSettings,
+    pub movement_profile: MovementProfile,
+    pub movement_zones: Vec<MovementZone>,
+    pub camera_zones: Vec<CameraZone>,
+    pub rewind_settings: RewindSettings,
     pub juice_particle_manager: juice_particles::ParticleManager,
-}
\ No newline at end of file
+    // Level-local clock, frames simulated since the level loaded. The flux cores pulse with it.
+    pub frame: u32,
+}
+
+impl Level {
+    pub fn update_checkpoints(&mut self, player_bound: &BoundingBox) {
+        for checkpoint in &mut self.checkpoints {
+            if checkpoint.is_reached || !checkpoint.solid.get_bound().intersects(player_bound) {
+                continue;
+            }
+            checkpoint.is_reached = true;
+
+            // Items carried at this point are dropped on respawn
+            let mut actor_manager = self.actor_manager.clone();
+            for actor in actor_manager.actors.values_mut() {
+                actor.is_child = false;
+            }
+
+            self.checkpoint_snapshot = Some(CheckpointSnapshot {
+                checkpoint_id: checkpoint.id,
+                respawn_position: checkpoint.solid.position,
+                harvesters: self.harvesters.clone(),
+                actor_manager,
+                open_doors: self.tilemap.doors.iter().map(|door| door.open).collect(),
+            });
+        }
+    }
+
+    pub fn get_triggered_exit(&self, player_positions: &[Vector2]) -> Option<ExitTarget> {
+        self.exits.iter()
+            .find(|exit| exit.is_triggered(player_positions))
+            .map(|exit| exit.target.clone())
+    }
+
+    // Slots beyond the level's spawn points share them round robin
+    pub fn get_player_start_position(&self, slot: usize) -> Vector2 {
+        self.player_start_positions[slot % self.player_start_positions.len()]
+    }
+
+    // Sum of what the harvesters collected during the last simulated frame
+    pub fn get_total_flux(&self) -> f32 {
+        self.harvesters.iter().map(|harvester| harvester.get_flux()).sum()
+    }
+
+    pub fn has_fallen_out(&self, player: &Player) -> bool {
+        self.tilemap.is_below(&player.get_position())
+    }
+
+    // Restores the state saved at the last reached checkpoint and returns the respawn position
+    pub fn restore_checkpoint(&mut self) -> Option<Vector2> {
+        match &self.checkpoint_snapshot {
+            Some(snapshot) => {
+                self.harvesters = snapshot.harvesters.clone();
+                self.actor_manager = snapshot.actor_manager.clone();
+                for (door, is_open) in self.tilemap.doors.iter_mut().zip(&snapshot.open_doors) {
+                    door.open = *is_open;
+                }
+                Some(snapshot.respawn_position)
+            },
+            None => None,
+        }
+    }
+}
@@ -37,6 +37,11 @@ impl LevelManager {
         }
     }
     
+    pub fn load_level(&mut self, level_name: LevelName) {
+        self.loaded_level = Self::construct_level(&level_name);
+        self.current_level = Some(level_name);
+    }
+
     pub fn reload_current_level(&mut self) {
         match &self.current_level {
             Some(level_name) => {
@@ -66,9 +71,20 @@ impl LevelManager {
 }
 
 #[turbo::serialize]
+#[derive(PartialEq)]
 pub enum LevelName {
     Level1,
     Level2,
     Level3,
     Level4,
-}
\ No newline at end of file
+}
+impl LevelName {
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            LevelName::Level1 => "Level 1",
+            LevelName::Level2 => "Level 2",
+            LevelName::Level3 => "Level 3",
+            LevelName::Level4 => "Level 4",
+        }
+    }
+}
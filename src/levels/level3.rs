@@ -129,8 +129,35 @@ pub fn construct_level_3() -> Level {
         }
     }
 
-    let player1_start_position = Vector2 { x: 100., y: 50. };
-    let player2_start_position = Vector2 { x: 400., y: 50. };
+    // One spawn point per co-op slot, the first one is also used in single player
+    let player_start_positions = vec![
+        Vector2 { x: 100., y: 50. },
+        Vector2 { x: 400., y: 50. },
+        Vector2 { x: 130., y: 50. },
+        Vector2 { x: 370., y: 50. },
+    ];
+
+    // Side by side, so both players start on the shared screen
+    let local_coop_start_positions = [
+        Vector2 { x: 100., y: 50. },
+        Vector2 { x: 130., y: 50. },
+    ];
+
+    let checkpoints = vec![
+        Checkpoint::new(0, 408., 248., tile_size as f32, tile_size as f32 * 3.),
+    ];
+
+    let exits = vec![
+        // In the door column, so players only get in once it opens
+        ExitZone::new(
+            (width as f32 - 0.5) * tile_size as f32,
+            248.,
+            tile_size as f32,
+            tile_size as f32 * 3.,
+            ExitTarget::NextLevel,
+            ExitRule::AllPlayers,
+        ),
+    ];
     
     let mut background = Background::new(0xfdfeecff);
     background.layers.push(Layer {
@@ -169,10 +196,22 @@ pub fn construct_level_3() -> Level {
         tilemap,
         harvesters,
         actor_manager,
-        player1_start_position,
-        player2_start_position,
+        player_start_positions,
+        local_coop_start_positions,
+        checkpoints,
+        checkpoint_snapshot: None,
+        exits,
         background,
         required_flux,
+        exposure_settings: ExposureSettings::new(EXPOSURE_THRESHOLD, EXPOSURE_FILL_RATE, EXPOSURE_DRAIN_RATE),
+        movement_profile: get_movement_profile(DEFAULT_MOVEMENT_PROFILE),
+        movement_zones: vec![],
+        rewind_settings: RewindSettings::new(REWIND_HISTORY_FRAMES, None),
+        camera_zones: vec![
+            // Under the middle platform the frame centers on the paired cores
+            CameraZone::new(25. * tile_size as f32, height as f32 * tile_size as f32 / 2., 18. * tile_size as f32, height as f32 * tile_size as f32, CameraZoneMode::Lock(Vector2::new(25. * tile_size as f32, 144.))),
+        ],
         juice_particle_manager: juice_particles::ParticleManager::new(),
+        frame: 0,
     }
 }
@@ -1,6 +1,6 @@
 use turbo::*;
-use std::collections::VecDeque;
-use std::io::{Error, ErrorKind};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Error;
 
 mod vector2;
 use vector2::*;
@@ -56,6 +56,87 @@ use main_menu::*;
 mod hud;
 use hud::*;
 
+mod exposure;
+use exposure::*;
+
+mod checkpoint;
+use checkpoint::*;
+
+mod exit_zone;
+use exit_zone::*;
+
+mod movement_profile;
+use movement_profile::*;
+
+mod debug_menu;
+use debug_menu::*;
+
+mod input;
+use input::*;
+
+mod settings;
+use settings::*;
+
+mod controls_menu;
+use controls_menu::*;
+
+mod coop_camera;
+use coop_camera::*;
+
+mod game_camera;
+use game_camera::*;
+
+mod prediction;
+use prediction::*;
+
+mod interpolation;
+use interpolation::*;
+
+mod snapshot;
+use snapshot::*;
+
+mod lobby;
+use lobby::*;
+
+mod lobby_menu;
+use lobby_menu::*;
+
+mod connection;
+use connection::*;
+
+mod player_slot;
+use player_slot::*;
+
+mod jitter_buffer;
+use jitter_buffer::*;
+
+mod transport;
+use transport::*;
+
+mod sim_rng;
+use sim_rng::*;
+
+mod checksum;
+use checksum::*;
+
+mod save_data;
+use save_data::*;
+
+mod replay;
+use replay::*;
+
+mod time_attack;
+use time_attack::*;
+
+mod speedrun;
+use speedrun::*;
+
+mod rewind;
+use rewind::*;
+
+#[cfg(feature = "harness")]
+pub mod harness;
+
 mod juice_particles;
 
 use core::fmt;
@@ -69,17 +150,41 @@ use crate::juice_particles::{BurstConfig, BurstSource, Shape};
 const SCREEN_WIDTH: i32 = 512;
 const SCREEN_HEIGHT: i32 = 288;
 const DEGAUSS_FRAMES: u32 = 120;
+const DEATH_FRAMES: u32 = 45;
+// Degauss frames looped while rewinding, the strongest part of the effect
+const REWIND_DEGAUSS_FRAMES: u32 = 20;
+const REWIND_HISTORY_FRAMES: u32 = 5 * 60;
 const FLUX_PER_UNIT: f32 = 200.;
-const DEATH_THRESHOLD: f32 = 30.;
+const AMBIENT_PARTICLE_MARGIN: f32 = 64.;
+const EXPOSURE_THRESHOLD: f32 = 30.;
+const EXPOSURE_FILL_RATE: f32 = 1. / 45.;
+const EXPOSURE_DRAIN_RATE: f32 = 1. / 120.;
+const EXPOSURE_MAX_DISTORTION: f32 = 60.;
+const PLAYER2_TINT: u32 = 0xffb070ff;
+const DEATH_TRAUMA: f32 = 0.8;
+const DOOR_OPEN_TRAUMA: f32 = 0.5;
+const DASH_TRAUMA: f32 = 0.25;
+const PREDICTION_BUFFER_SIZE: usize = 120;
+const SERVER_TICK_MS: u32 = 20;
+// Clients simulate and send one input per frame at 60 fps
+const CLIENT_FRAME_MS: f32 = 1000. / 60.;
+// Every input packet repeats this many of the newest unacknowledged inputs
+const REDUNDANT_INPUT_COUNT: usize = 4;
 
 #[turbo::game]
 struct GameState {
     level_manager: LevelManager,
     local_player: Player,
+    // Second player of local co-op, driven by the second gamepad
+    local_player2: Player,
     server_player_position: Vector2,
-    unprocessed_local_inputs: VecDeque<UserInput>,
-    remote_player_snapshots: VecDeque<Player>,
-    max_player_snapshots: usize,
+    prediction_buffer: PredictionBuffer,
+    // One interpolator per remote player, keyed by player id
+    remote_player_interpolators: BTreeMap<String, SnapshotInterpolator>,
+    // Partners the server is holding the game for
+    disconnected_partner_ids: Vec<String>,
+    // Decoded server snapshots, the newest one is acknowledged as the delta baseline
+    snapshot_history: SnapshotHistory,
     frames_per_server_update: u32,
     last_fpsu:u32,
     last_processed_tick:usize,
@@ -87,28 +192,79 @@ struct GameState {
     game_flow_state: GameFlowState,
     particle_manager: ParticleManager,
     degauss_shader_counter: u32,
+    death_timer: u32,
     hud: Hud,
+    debug_menu: DebugMenu,
+    settings: Settings,
+    input_map: InputMap,
+    input_map2: InputMap,
+    controls_menu: ControlsMenu,
+    lobby_menu: LobbyMenu,
+    // Code of the co-op room, also the id of its game channel
+    room_code: String,
+    // Filled from the server while waiting in the room
+    room_player_count: u32,
+    room_ready_count: u32,
+    connection_status: ConnectionStatus,
+    camera: Camera,
+    // Inputs of the current single-player level, saved when it is completed
+    replay_recorder: Option<Recording>,
+    // Set while a saved run is played back instead of reading the controller
+    replay_playback: Option<ReplayPlayback>,
+    replay_menu_options: Vec<MenuOption>,
+    // The attempt in progress while playing a level in time-attack mode
+    time_attack: Option<TimeAttack>,
+    time_attack_results: Option<TimeAttackResults>,
+    time_attack_menu_options: Vec<MenuOption>,
+    // Times the story run started from START across all its levels
+    speedrun: Option<SpeedrunTimer>,
+    // Recent single-player frames to step back through
+    rewind: RewindBuffer,
 }
 
 impl GameState {
     pub fn new() -> Self {
         let level_manager =  LevelManager::new();
-        let local_player_position = level_manager.loaded_level.player1_start_position.clone();
+        let local_player_position = level_manager.loaded_level.get_player_start_position(0);
+        let local_player2_position = level_manager.loaded_level.local_coop_start_positions[1];
+        let settings = Settings::load();
         Self {
-            particle_manager: ParticleManager::new(level_manager.loaded_level.tilemap.flux_cores.clone()),
+            input_map: InputMap::new(0, true, settings.bindings.clone()),
+            input_map2: InputMap::new(1, false, settings.player2_bindings.clone()),
+            local_player2: Player::new(local_player2_position.x, local_player2_position.y),
+            settings,
+            controls_menu: ControlsMenu::new(),
+            lobby_menu: LobbyMenu::new(),
+            room_code: String::new(),
+            room_player_count: 0,
+            room_ready_count: 0,
+            connection_status: ConnectionStatus::Connected,
+            camera: Camera::new(local_player_position),
+            replay_recorder: None,
+            replay_playback: None,
+            replay_menu_options: vec![],
+            time_attack: None,
+            time_attack_results: None,
+            time_attack_menu_options: vec![],
+            speedrun: None,
+            rewind: RewindBuffer::new(),
+            particle_manager: ParticleManager::new(level_manager.loaded_level.tilemap.flux_cores.clone(), level_manager.loaded_level.exposure_settings.threshold),
             level_manager,
             local_player: Player::new(local_player_position.x, local_player_position.y),
             server_player_position: Vector2::zero(),
-            unprocessed_local_inputs: VecDeque::new(),
-            remote_player_snapshots: VecDeque::new(),
-            max_player_snapshots: 3,
+            prediction_buffer: PredictionBuffer::new(PREDICTION_BUFFER_SIZE),
+            remote_player_interpolators: BTreeMap::new(),
+            disconnected_partner_ids: vec![],
+            snapshot_history: SnapshotHistory::new(),
             frames_per_server_update: 0,
             last_fpsu: 0,
             last_processed_tick: 0,
             main_menu_options: get_main_menu_options(),
             game_flow_state: GameFlowState::MainMenu,
             degauss_shader_counter: 0,
+            death_timer: 0,
             hud: Hud::new(),
+            debug_menu: DebugMenu::new(),
         }
     }
     
@@ -125,12 +281,30 @@ impl GameState {
             GameFlowState::InGameCoOp => {
                 self.handle_in_game_flow();
             },
+            GameFlowState::InGameLocalCoOp => {
+                self.handle_in_game_flow();
+            },
             GameFlowState::WaitingForPlayer2 => {
                 self.handle_waiting_for_player_2_flow();
             },
+            GameFlowState::Lobby => {
+                self.handle_lobby_flow();
+            },
             GameFlowState::Credits => {
                 self.handle_credits_flow();
-            }
+            },
+            GameFlowState::Controls => {
+                self.handle_controls_flow();
+            },
+            GameFlowState::Replays => {
+                self.handle_replays_flow();
+            },
+            GameFlowState::TimeAttackMenu => {
+                self.handle_time_attack_menu_flow();
+            },
+            GameFlowState::TimeAttackResults => {
+                self.handle_time_attack_results_flow();
+            },
         }
 
         if self.degauss_shader_counter > 0 {
@@ -149,7 +323,7 @@ impl GameState {
     fn handle_waiting_for_player_2_flow(&mut self) {
         set_xy(SCREEN_WIDTH as f32 / 2., SCREEN_HEIGHT as f32 / 2.);
 
-        if let Some(conn) = FluxGameStateChannel::subscribe("default") { 
+        if let Some(conn) = FluxGameStateChannel::subscribe(&self.room_code) { 
             while let Ok(msg) = conn.recv() { 
                 match msg {
                     ServerMsg::ConnectionSuccessful { player_id } => {
@@ -158,13 +332,30 @@ impl GameState {
                             self.local_player.id = player_id;
                         }
                     },
+                    ServerMsg::LobbyState { player_count, ready_count } => {
+                        self.room_player_count = player_count;
+                        self.room_ready_count = ready_count;
+                    },
                     ServerMsg::StartGame  => {
                         self.game_flow_state = GameFlowState::InGameCoOp;
                     },
                     _ => {},
                 }
             }
-            let _ = conn.send(&ClientMsg::Ready );
+            // The game starts once everyone in the room is ready, so late joiners still make it in
+            if self.input_map.just_pressed(Action::Confirm) {
+                let _ = conn.send(&ClientMsg::Ready );
+            }
+        }
+
+        // The host can still back out while nobody joined
+        if self.input_map.just_pressed(Action::Back) {
+            if let Some(conn) = LobbyChannel::subscribe(LOBBY_DIRECTORY_ID) {
+                let _ = conn.send(&LobbyClientMsg::CloseRoom { code: self.room_code.clone() });
+            }
+            self.lobby_menu = LobbyMenu::new();
+            self.game_flow_state = GameFlowState::Lobby;
+            return;
         }
         sprite!(
             "UI_MainMenuScreen",
@@ -186,33 +377,99 @@ impl GameState {
             color = 0xffffffff,
             font = "large",
         );
-        let mut player_2_string = "Player 2".to_string();
+        let mut players_string = "Players".to_string();
         let time_tick = time::tick() % (4 * 60);
         if time_tick > 60 {
-            player_2_string.push('.');
+            players_string.push('.');
         }
         if time_tick > 2 * 60 {
-            player_2_string.push('.');
+            players_string.push('.');
         }
         if time_tick > 3 * 60 {
-            player_2_string.push('.');
+            players_string.push('.');
         }
         text!(
-            &player_2_string,
+            &players_string,
             x = 140,
             y = 140,
             color = 0xffffffff,
             font = "large",
         );
+        let ready_string = format!("{}/{} ready, confirm to start", self.room_ready_count, self.room_player_count);
+        text!(
+            &ready_string,
+            x = SCREEN_WIDTH / 2 - ready_string.len() as i32 * 4,
+            y = 185,
+            color = 0xffffffff,
+            font = "large",
+        );
+        let room_code_string = format!("Room {}", self.room_code);
+        text!(
+            &room_code_string,
+            x = 144,
+            y = 165,
+            color = 0x4be5feff,
+            font = "large",
+        );
+
+        draw_menu_distortion_parameter_pixel();
+    }
+    
+    fn handle_lobby_flow(&mut self) {
+        set_xy(SCREEN_WIDTH as f32 / 2., SCREEN_HEIGHT as f32 / 2.);
 
+        if let Some(conn) = LobbyChannel::subscribe(LOBBY_DIRECTORY_ID) {
+            while let Ok(msg) = conn.recv() {
+                match msg {
+                    LobbyServerMsg::Created { code } | LobbyServerMsg::Joined { code } => {
+                        if self.lobby_menu.is_connecting() {
+                            self.room_code = code;
+                            self.connection_status = ConnectionStatus::Connected;
+                            self.disconnected_partner_ids.clear();
+                            self.room_player_count = 0;
+                            self.room_ready_count = 0;
+                            self.game_flow_state = GameFlowState::WaitingForPlayer2;
+                            return;
+                        }
+                    },
+                    LobbyServerMsg::Unavailable { code } => {
+                        self.lobby_menu.show_error(format!("Room {} is not available", code));
+                    },
+                    LobbyServerMsg::OpenRooms { codes } => {
+                        self.lobby_menu.set_open_rooms(codes);
+                    },
+                }
+            }
+
+            let request = match self.lobby_menu.handle_input(&self.input_map) {
+                Some(LobbyAction::CreateRoom) => Some(LobbyClientMsg::CreateRoom),
+                Some(LobbyAction::JoinRoom(code)) => Some(LobbyClientMsg::JoinRoom { code }),
+                Some(LobbyAction::RefreshRooms) => Some(LobbyClientMsg::ListRooms),
+                Some(LobbyAction::Leave) => {
+                    self.game_flow_state = GameFlowState::MainMenu;
+                    return;
+                },
+                None => None,
+            };
+            if let Some(request) = request {
+                let _ = conn.send(&request);
+            }
+        }
+
+        sprite!(
+            "UI_MainMenuScreen",
+            x = 0,
+            y = 0,
+            fixed = true,
+        );
+        self.lobby_menu.draw();
         draw_menu_distortion_parameter_pixel();
     }
     
     fn handle_credits_flow(&mut self) {
         set_xy(SCREEN_WIDTH as f32 / 2., SCREEN_HEIGHT as f32 / 2.);
 
-        let gamepad = gamepad::get(0);
-        if gamepad.a.just_pressed() || gamepad.b.just_pressed() || gamepad.x.just_pressed() || gamepad.y.just_pressed() || gamepad.start.just_pressed() || gamepad.select.just_pressed() {
+        if self.input_map.any_just_pressed() {
             self.game_flow_state = GameFlowState::MainMenu;
         }
 
@@ -282,15 +539,35 @@ impl GameState {
             y = 0,
             fixed = true,
         );
-        let selected_option = handle_input(&mut self.main_menu_options);
+        let selected_option = handle_input(&mut self.main_menu_options, &self.input_map);
         match selected_option {
             Some(text) => {
                 if text == "START" {
                     self.game_flow_state = GameFlowState::InGameSingle;
                     self.reload_game();
+                    self.time_attack = None;
+                    self.start_recording();
+                    self.start_speedrun();
+                    return;
+                } else if text == "Time Attack" {
+                    self.time_attack_menu_options = get_time_attack_menu_options(&SaveData::load());
+                    self.game_flow_state = GameFlowState::TimeAttackMenu;
                     return;
                 } else if text == "Co-Op" {
-                    self.game_flow_state = GameFlowState::WaitingForPlayer2;
+                    self.lobby_menu = LobbyMenu::new();
+                    self.game_flow_state = GameFlowState::Lobby;
+                    return;
+                } else if text == "Local Co-Op" {
+                    self.game_flow_state = GameFlowState::InGameLocalCoOp;
+                    self.reload_game();
+                    return;
+                } else if text == "Replays" {
+                    self.replay_menu_options = get_replay_menu_options(&SaveData::load());
+                    self.game_flow_state = GameFlowState::Replays;
+                    return;
+                } else if text == "Controls" {
+                    self.controls_menu = ControlsMenu::new();
+                    self.game_flow_state = GameFlowState::Controls;
                     return;
                 } else if text == "Credits" {
                     self.game_flow_state = GameFlowState::Credits;
@@ -303,106 +580,396 @@ impl GameState {
         draw_menu_distortion_parameter_pixel();
     }
     
+    fn handle_controls_flow(&mut self) {
+        set_xy(SCREEN_WIDTH as f32 / 2., SCREEN_HEIGHT as f32 / 2.);
+
+        if self.controls_menu.handle_input(&mut self.input_map, &mut self.input_map2) {
+            self.settings.bindings = self.input_map.bindings.clone();
+            self.settings.player2_bindings = self.input_map2.bindings.clone();
+            self.settings.save();
+            self.game_flow_state = GameFlowState::MainMenu;
+        }
+
+        sprite!(
+            "UI_MainMenuScreen",
+            x = 0,
+            y = 0,
+            fixed = true,
+        );
+        self.controls_menu.draw(&self.input_map, &self.input_map2);
+        draw_menu_distortion_parameter_pixel();
+    }
+    
+    fn handle_replays_flow(&mut self) {
+        set_xy(SCREEN_WIDTH as f32 / 2., SCREEN_HEIGHT as f32 / 2.);
+
+        let selected_option = handle_input(&mut self.replay_menu_options, &self.input_map);
+        if self.input_map.just_pressed(Action::Back) || selected_option.as_deref() == Some("Back") {
+            self.game_flow_state = GameFlowState::MainMenu;
+            return;
+        }
+        if let Some(text) = selected_option {
+            let save_data = SaveData::load();
+            if text == EXPORT_RUNS_OPTION {
+                export_run_history(&save_data.speedrun_history);
+            }
+            if let Some(recording) = save_data.replays.iter().find(|recording| recording.level_name.get_display_name() == text) {
+                self.start_replay(recording.clone());
+                return;
+            }
+        }
+
+        sprite!(
+            "UI_MainMenuScreen",
+            x = 0,
+            y = 0,
+            fixed = true,
+        );
+        text!("REPLAYS", x = SCREEN_WIDTH / 2 - 28, y = 30, color = 0xffffffff, font = "large");
+        if self.replay_menu_options.iter().all(|option| option.text == "Back" || option.text == EXPORT_RUNS_OPTION) {
+            text!("Complete a level to record a replay", x = SCREEN_WIDTH / 2 - 140, y = 120, color = 0xffffffff, font = "large");
+        }
+        for option in &self.replay_menu_options {
+            option.draw();
+        }
+        draw_menu_distortion_parameter_pixel();
+    }
+
+    fn handle_time_attack_menu_flow(&mut self) {
+        set_xy(SCREEN_WIDTH as f32 / 2., SCREEN_HEIGHT as f32 / 2.);
+
+        let selected_option = handle_input(&mut self.time_attack_menu_options, &self.input_map);
+        if self.input_map.just_pressed(Action::Back) || selected_option.as_deref() == Some("Back") {
+            self.game_flow_state = GameFlowState::MainMenu;
+            return;
+        }
+        let selected_level = selected_option
+            .and_then(|text| self.time_attack_menu_options.iter().position(|option| option.text == text))
+            .and_then(|idx| TIME_ATTACK_LEVELS.get(idx));
+        if let Some(level_name) = selected_level {
+            self.start_time_attack(level_name.clone());
+            return;
+        }
+
+        sprite!(
+            "UI_MainMenuScreen",
+            x = 0,
+            y = 0,
+            fixed = true,
+        );
+        text!("TIME ATTACK", x = SCREEN_WIDTH / 2 - 44, y = 30, color = 0xffffffff, font = "large");
+        for option in &self.time_attack_menu_options {
+            option.draw();
+        }
+        draw_menu_distortion_parameter_pixel();
+    }
+
+    fn handle_time_attack_results_flow(&mut self) {
+        set_xy(SCREEN_WIDTH as f32 / 2., SCREEN_HEIGHT as f32 / 2.);
+
+        // Retries the same level right away
+        match self.time_attack_results.as_ref().map(|results| results.run.level_name.clone()) {
+            Some(level_name) if self.input_map.just_pressed(Action::Confirm) => {
+                self.start_time_attack(level_name);
+                return;
+            },
+            _ => {},
+        }
+        if self.input_map.just_pressed(Action::Back) {
+            self.time_attack_menu_options = get_time_attack_menu_options(&SaveData::load());
+            self.game_flow_state = GameFlowState::TimeAttackMenu;
+            return;
+        }
+
+        sprite!(
+            "UI_MainMenuScreen",
+            x = 0,
+            y = 0,
+            fixed = true,
+        );
+        if let Some(results) = &self.time_attack_results {
+            results.draw();
+        }
+        draw_menu_distortion_parameter_pixel();
+    }
+
     fn handle_in_game_flow(&mut self) {
-        let gamepad = gamepad::get(0);
-        let user_input = UserInput {
-            tick: time::tick(),
-            jump_just_pressed: gamepad.b.just_pressed(),
-            jump_pressed: gamepad.b.pressed(),
-            left_pressed: gamepad.left.pressed(),
-            right_pressed: gamepad.right.pressed(),
-            up_pressed: gamepad.up.pressed(),
-            down_pressed: gamepad.down.pressed(),
-            a_just_pressed: gamepad.a.just_pressed(),
-            x_just_pressed: gamepad.x.just_pressed(),
-            y_just_pressed: gamepad.y.just_pressed(),
+        let mut user_input = self.input_map.get_user_input(time::tick());
+        let mut user_input2 = self.input_map2.get_user_input(time::tick());
+        let is_local_coop = matches!(self.game_flow_state, GameFlowState::InGameLocalCoOp);
+
+        let replay_frame_count = match &mut self.replay_playback {
+            Some(playback) => {
+                // Back shares Pad A with Pick, the same as in time attacks
+                if self.input_map.just_pressed(Action::Quit) {
+                    self.replay_playback = None;
+                    self.game_flow_state = GameFlowState::MainMenu;
+                    return;
+                }
+                playback.handle_input(&self.input_map)
+            },
+            None => 0,
         };
+        // Back shares Pad A with Pick, so leaving the attempt has its own action
+        if self.time_attack.is_some() && self.input_map.just_pressed(Action::Quit) {
+            self.time_attack = None;
+            self.time_attack_menu_options = get_time_attack_menu_options(&SaveData::load());
+            self.game_flow_state = GameFlowState::TimeAttackMenu;
+            return;
+        }
+
+        // The debug menu could switch the movement profile under a replay
+        if self.input_map.just_pressed(Action::DebugMenu) && self.replay_playback.is_none() {
+            self.debug_menu.toggle();
+        }
+        if self.debug_menu.is_open {
+            if let Some(profile_name) = self.debug_menu.handle_input(&self.input_map) {
+                self.set_movement_profile(profile_name);
+            }
+            // Navigating the menu shouldn't move the player
+            user_input = UserInput { tick: user_input.tick, ..UserInput::new() };
+            user_input2 = UserInput { tick: user_input2.tick, ..UserInput::new() };
+        }
         
         if matches!(self.game_flow_state, GameFlowState::InGameCoOp) {
-            if let Some(conn) = FluxGameStateChannel::subscribe("default") { 
+            if let Some(conn) = FluxGameStateChannel::subscribe(&self.room_code) { 
                 while let Ok(msg) = conn.recv() { 
                     match msg {
-                        ServerMsg::GameState { harvesters, actor_manager, player1, last_processed_tick_p1, player2, last_processed_tick_p2 } => {
-                            self.level_manager.loaded_level.harvesters = harvesters;
-                            self.level_manager.loaded_level.actor_manager = actor_manager;
-                            if self.local_player.id == player1.id  {
-                                self.server_player_position = player1.actor.position.clone();
-                                self.local_player = player1;
-
-                                match last_processed_tick_p1 {
-                                    Some(last_tick) => {
-                                        while let Some(user_input) = self.unprocessed_local_inputs.pop_front() {
-                                            self.last_processed_tick = last_tick;
-                                            if user_input.tick > last_tick {
-                                                self.unprocessed_local_inputs.push_front(user_input);
-                                                break;
-                                            }
-                                        }
-                                    },
-                                    None => {},
-                                }
-
-                                self.remote_player_snapshots.push_front(player2);
-                            } else if self.local_player.id == player2.id {
-                                self.server_player_position = player2.actor.position.clone();
-                                self.local_player = player2;
-
-                                match last_processed_tick_p2 {
-                                    Some(last_tick) => {
-                                        while let Some(user_input) = self.unprocessed_local_inputs.pop_front() {
-                                            self.last_processed_tick = last_tick;
-                                            if user_input.tick > last_tick {
-                                                self.unprocessed_local_inputs.push_front(user_input);
-                                                break;
-                                            }
-                                        }
-                                    },
-                                    None => {},
-                                }
-
-                                self.remote_player_snapshots.push_front(player1);
+                        ServerMsg::GameState { snapshot, last_processed_tick, checksum } => {
+                            // Deltas against a baseline we no longer have are dropped, the next ack fixes it
+                            let WorldSnapshot { server_tick, level_frame, mut players, harvesters, actor_manager } = match self.snapshot_history.decode(snapshot) {
+                                Some(snapshot) => snapshot,
+                                None => continue,
+                            };
+                            let server_local_player = match players.remove(&self.local_player.id) {
+                                Some(player) => player,
+                                None => continue,
+                            };
+                            self.server_player_position = server_local_player.actor.position;
+
+                            self.remote_player_interpolators.retain(|id, _| players.contains_key(id));
+                            for (id, remote_player) in players {
+                                self.remote_player_interpolators.entry(id).or_insert_with(SnapshotInterpolator::new).push(server_tick, remote_player, time::now());
                             }
-                            
-                            if self.remote_player_snapshots.len() > self.max_player_snapshots {
-                                self.remote_player_snapshots.pop_back();
-                            }
-                            
-                            // Replay unprocessed commands
-                            for input in &self.unprocessed_local_inputs {
-                                simulate_frame(&mut self.local_player, &mut self.level_manager.loaded_level, input);
+
+                            if let Some(last_tick) = last_processed_tick {
+                                self.last_processed_tick = last_tick;
                             }
+                            reconcile_local_state(
+                                &mut self.local_player,
+                                &mut self.level_manager.loaded_level,
+                                &mut self.prediction_buffer,
+                                last_processed_tick,
+                                ServerState { level_frame, player: server_local_player, harvesters, actor_manager, checksum },
+                            );
                             
                             self.last_fpsu = self.frames_per_server_update;
                             self.frames_per_server_update = 0;
+                            if self.connection_status == ConnectionStatus::Reconnecting {
+                                self.connection_status = ConnectionStatus::Connected;
+                            }
+                        },
+                        ServerMsg::PlayerDisconnected { player_id } if self.local_player.id != player_id => {
+                            self.disconnected_partner_ids.push(player_id);
+                            self.connection_status = ConnectionStatus::PartnerDisconnected;
+                        },
+                        ServerMsg::PlayerReconnected { player_id } => {
+                            self.disconnected_partner_ids.retain(|id| *id != player_id);
+                            if self.disconnected_partner_ids.is_empty() && self.connection_status != ConnectionStatus::PartnerLeft {
+                                self.connection_status = ConnectionStatus::Connected;
+                            }
+                        },
+                        // The game goes on with whoever is left, alone there is nothing to play
+                        ServerMsg::PlayerLeft { player_id } if self.local_player.id != player_id => {
+                            self.disconnected_partner_ids.retain(|id| *id != player_id);
+                            self.remote_player_interpolators.remove(&player_id);
+                            if self.remote_player_interpolators.is_empty() {
+                                self.connection_status = ConnectionStatus::PartnerLeft;
+                            } else if self.disconnected_partner_ids.is_empty() {
+                                self.connection_status = ConnectionStatus::Connected;
+                            }
+                        },
+                        // Sent when the room went back to the lobby after the partner left
+                        ServerMsg::LobbyState { player_count, ready_count } => {
+                            self.room_player_count = player_count;
+                            self.room_ready_count = ready_count;
+                        },
+                        ServerMsg::Resync { level_name, profile_name } => {
+                            // Level changes may have been missed while the connection was down
+                            match level_name {
+                                Some(level_name) if Some(&level_name) != self.level_manager.current_level.as_ref() => {
+                                    self.level_manager.load_level(level_name);
+                                    self.reset_level_state();
+                                },
+                                _ => {},
+                            }
+                            self.level_manager.loaded_level.movement_profile = get_movement_profile(&profile_name);
                         },
                         ServerMsg::GameCompleted => {
                             log!("Completed game");
                             self.game_flow_state = GameFlowState::Credits;
                         },
-                        ServerMsg::LevelCompleted => {
+                        ServerMsg::LevelCompleted { exit_target } => {
                             log!("Completed level");
-                            self.load_next_level();
+                            self.take_exit(exit_target);
+                        },
+                        ServerMsg::MovementProfileChanged { profile_name } => {
+                            self.level_manager.loaded_level.movement_profile = get_movement_profile(&profile_name);
+                        },
+                        ServerMsg::PlayerDied { player_id } => {
+                            if self.local_player.id == player_id {
+                                self.start_death_sequence();
+                            } else if let Some(remote_player) = self.remote_player_interpolators.get(&player_id).and_then(|interpolator| interpolator.get_latest()) {
+                                remote_player.generate_death_particles(&mut self.level_manager.loaded_level.juice_particle_manager);
+                            }
                         },
                         _ => {},
                     }
                 }
 
-                // Send gamepad state to the server
-                let _ = conn.send(&ClientMsg::UserInput { user_input: user_input.clone() });
+                if self.connection_status == ConnectionStatus::Connected && self.frames_per_server_update > CONNECTION_TIMEOUT_FRAMES {
+                    self.connection_status = ConnectionStatus::Reconnecting;
+                }
+
+                // The server respawned us already, buttons held through the death sequence would
+                // move the new player while the client still shows the old one dying
+                if self.death_timer > 0 {
+                    user_input = UserInput { tick: user_input.tick, ..UserInput::new() };
+                }
+
+                // Send gamepad state to the server, the game is frozen for both players while paused
+                if !self.connection_status.is_paused() {
+                    let mut user_inputs = self.prediction_buffer.get_recent_inputs(REDUNDANT_INPUT_COUNT - 1);
+                    user_inputs.push(user_input.clone());
+                    let _ = conn.send(&ClientMsg::UserInput {
+                        user_inputs,
+                        acknowledged_snapshot: self.snapshot_history.get_latest_tick(),
+                    });
+                }
             }
 
-            self.unprocessed_local_inputs.push_back(user_input.clone());
+            // Alone in the room, the slot can go to someone new once everyone readied up again
+            if self.connection_status == ConnectionStatus::PartnerLeft && self.input_map.just_pressed(Action::Confirm) {
+                self.connection_status = ConnectionStatus::Connected;
+                self.game_flow_state = GameFlowState::WaitingForPlayer2;
+                return;
+            }
+            if self.connection_status.is_paused() && self.input_map.just_pressed(Action::Back) {
+                self.connection_status = ConnectionStatus::Connected;
+                self.game_flow_state = GameFlowState::MainMenu;
+                return;
+            }
         }
+        let is_network_paused = matches!(self.game_flow_state, GameFlowState::InGameCoOp) && self.connection_status.is_paused();
         
-        simulate_frame(&mut self.local_player, &mut self.level_manager.loaded_level, &user_input);
+        // Replays and time attacks have to play out frame by frame
+        let can_rewind = matches!(self.game_flow_state, GameFlowState::InGameSingle)
+            && self.replay_playback.is_none()
+            && self.time_attack.is_none()
+            && !self.debug_menu.is_open;
+        let is_rewinding = can_rewind && self.update_rewind();
+
+        let open_doors_before = self.level_manager.loaded_level.tilemap.doors.iter().filter(|door| door.open).count();
+        let was_dashing = self.local_player.is_dashing() || (is_local_coop && self.local_player2.is_dashing());
+        if is_network_paused {
+            // Nothing moves until the server resumes the game
+        } else if is_rewinding {
+            self.rewind.step_back(&mut self.local_player, &mut self.level_manager.loaded_level);
+        } else if self.death_timer > 0 {
+            self.death_timer -= 1;
+            // In co-op the server respawns the player
+            if self.death_timer == 0 && matches!(self.game_flow_state, GameFlowState::InGameSingle) {
+                self.respawn_local_player();
+            }
+        } else if is_local_coop {
+            let inputs = [user_input.clone(), user_input2.clone()];
+            let previous_positions = [self.local_player.get_position(), self.local_player2.get_position()];
+            simulate_server_frame(&mut [&mut self.local_player, &mut self.local_player2], &inputs, &mut self.level_manager.loaded_level);
+            self.handle_local_coop_deaths();
+            leash_local_players(&mut self.local_player, &mut self.local_player2, &previous_positions);
+        } else if let Some(playback) = &mut self.replay_playback {
+            // A death ends the update, so the death sequence plays out like it did in the recorded run
+            for _ in 0..replay_frame_count {
+                let replay_input = match playback.next_input() {
+                    Some(replay_input) => replay_input,
+                    None => break,
+                };
+                simulate_frame(&mut self.local_player, &mut self.level_manager.loaded_level, &replay_input);
+                if self.local_player.exposure.is_lethal() || self.level_manager.loaded_level.has_fallen_out(&self.local_player) {
+                    break;
+                }
+            }
+        } else {
+            simulate_frame(&mut self.local_player, &mut self.level_manager.loaded_level, &user_input);
+            if let (Some(recording), GameFlowState::InGameSingle) = (&mut self.replay_recorder, &self.game_flow_state) {
+                recording.push(&user_input);
+            }
+            if can_rewind {
+                self.rewind.record(&self.local_player, &self.level_manager.loaded_level);
+            }
+        }
+        // In-game time only runs on frames that advanced the run, so the debug menu, a network
+        // pause and rewinding don't count. The death sequence does, dying costs its time.
+        let is_run_advancing = !self.debug_menu.is_open && !is_network_paused && !is_rewinding;
+        match &mut self.speedrun {
+            Some(speedrun) if is_run_advancing => speedrun.tick(),
+            _ => {},
+        }
+        if let Some(time_attack) = &mut self.time_attack {
+            time_attack.record_frame(&self.local_player, self.death_timer == 0, &self.level_manager.loaded_level);
+        }
+        if matches!(self.game_flow_state, GameFlowState::InGameCoOp) && !is_network_paused {
+            self.prediction_buffer.record(&user_input, &self.local_player, &self.level_manager.loaded_level);
+        }
 
-        let bounding_box = &BoundingBox { top: -10., right: 700., bottom: 300., left: -10. };
+        let open_doors_after = self.level_manager.loaded_level.tilemap.doors.iter().filter(|door| door.open).count();
+        if open_doors_after > open_doors_before {
+            self.camera.add_trauma(DOOR_OPEN_TRAUMA);
+        }
+        let is_dashing = self.local_player.is_dashing() || (is_local_coop && self.local_player2.is_dashing());
+        if is_dashing && !was_dashing {
+            self.camera.add_trauma(DASH_TRAUMA);
+        }
+
+        let now = time::now();
+        let interpolated_remote_players: Vec<Player> = self.remote_player_interpolators.values_mut()
+            .filter_map(|interpolator| interpolator.sample(now))
+            .collect();
+        let partner_positions: Vec<Vector2> = if is_local_coop {
+            vec![self.local_player2.get_position()]
+        } else {
+            interpolated_remote_players.iter().map(|player| player.get_position()).collect()
+        };
+
+        // Keep all co-op players in frame and only follow the local player when they can't fit
+        let local_position = self.local_player.get_position();
+        let shared_camera_position = if is_local_coop {
+            // The leash keeps same-screen players together, so their frame never gives up
+            Some(get_group_camera_position(&self.level_manager.loaded_level.tilemap, &local_position, &partner_positions))
+        } else {
+            get_shared_camera_position(&self.level_manager.loaded_level.tilemap, &local_position, &partner_positions)
+        };
+        match &shared_camera_position {
+            Some(shared_camera_position) => self.camera.follow_directly(shared_camera_position),
+            None => self.camera.follow(&local_position, self.local_player.get_look_direction(), self.local_player.is_grounded()),
+        }
+        // Zones frame a single player, a shared frame already has to hold everyone
+        let camera_zones: &[CameraZone] = if shared_camera_position.is_some() { &[] } else { &self.level_manager.loaded_level.camera_zones };
+        self.camera.update(&self.level_manager.loaded_level.tilemap, camera_zones);
+        let camera_position = self.camera.get_position();
+
+        // Ambient particles only spawn around the viewport so large levels don't exhaust the particle pool
+        let tilemap_bound = self.level_manager.loaded_level.tilemap.get_bound().expand(10.);
+        let spawn_bound = BoundingBox::from_center(&camera_position, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32).expand(AMBIENT_PARTICLE_MARGIN);
         if 0 == time::tick() % 3 {
             for flux_core in &self.level_manager.loaded_level.tilemap.flux_cores {
-                
+                if !flux_core.solid.get_bound().intersects(&spawn_bound) {
+                    continue;
+                }
+
                 match flux_core.core_type {
                     FluxCoreType::Radial => {
-                        if flux_core.get_strength() > 0. {
+                        if flux_core.get_strength(self.level_manager.loaded_level.frame) > 0. {
                             self.particle_manager.generate_box_of_particles(1 as u32, &flux_core.solid.get_bound());
                         }
                     },
@@ -419,28 +986,29 @@ impl GameState {
             }
         }
 
-        self.particle_manager.generate_box_of_particles(time::tick() as u32 % 2, bounding_box);
-        self.particle_manager.update(&self.level_manager.loaded_level.tilemap.flux_cores);
+        if spawn_bound.intersects(&tilemap_bound) {
+            self.particle_manager.generate_box_of_particles(time::tick() as u32 % 2, &spawn_bound.intersection(&tilemap_bound));
+        }
+        self.particle_manager.update(&self.level_manager.loaded_level.tilemap.flux_cores, self.level_manager.loaded_level.frame);
 
         self.level_manager.loaded_level.juice_particle_manager.update();
 
-        let camera_position = self.level_manager.loaded_level.tilemap.lock_viewport_to_tilemap(
-            &Vector2::new(self.local_player.actor.position.x, self.local_player.actor.position.y),
-            &Vector2::new(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32)
-        );
-        
-        pan_xy((camera_position.x as i32, camera_position.y as i32), 40, Easing::EaseOutQuad);
+        self.camera.apply();
 
         let screen_bounds = bounds::world();
         let screen_center = screen_bounds.center();
-        self.level_manager.loaded_level.background.draw(Vector2{ x: screen_center.0 as f32, y: screen_center.1 as f32 });
+        let screen_center = Vector2{ x: screen_center.0 as f32, y: screen_center.1 as f32 };
+        let viewport_bound = BoundingBox::from_center(&screen_center, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+        self.level_manager.loaded_level.background.draw(screen_center);
 
-        self.particle_manager.draw();
+        self.particle_manager.draw(self.level_manager.loaded_level.frame);
         self.level_manager.loaded_level.juice_particle_manager.draw();
 
-        //self.level.tilemap.draw_flux_field();
+        //self.level.tilemap.draw_flux_field(self.level.frame);
         for t in &self.level_manager.loaded_level.tilemap.tiles {
-            t.draw();
+            if t.solid.get_bound().intersects(&viewport_bound) {
+                t.draw();
+            }
         }
         
         for f in &self.level_manager.loaded_level.tilemap.flux_cores {
@@ -450,19 +1018,31 @@ impl GameState {
         for d in &self.level_manager.loaded_level.tilemap.doors {
             d.draw();
         }
+
+        for c in &self.level_manager.loaded_level.checkpoints {
+            c.draw();
+        }
+
+        for movement_zone in &self.level_manager.loaded_level.movement_zones {
+            movement_zone.draw();
+        }
         
-        self.local_player.draw();
-        let interpolated_remote_player = self.remote_player_snapshots.back().cloned();
-        let mut snapshot_positions: Vec<Vector2> = vec![];
-        for remote_player in &self.remote_player_snapshots {
-            snapshot_positions.push(remote_player.actor.position.clone());
-        }
-        match interpolated_remote_player {
-            Some(mut player) => {
-                player.actor.position = smooth_position(snapshot_positions);
-                player.draw();
-            },
-            None => {}
+        if let Some(time_attack) = &self.time_attack {
+            time_attack.draw_ghost();
+        }
+        if self.death_timer == 0 {
+            self.local_player.draw();
+        }
+        if is_local_coop {
+            self.local_player2.draw_with_color(PLAYER2_TINT);
+        }
+        for player in &interpolated_remote_players {
+            player.draw();
+        }
+        if shared_camera_position.is_none() {
+            for partner_position in &partner_positions {
+                draw_partner_indicator(&screen_center, &local_position, partner_position);
+            }
         }
         //self.server_player_position.draw(5);
         self.level_manager.loaded_level.harvesters.iter().for_each( |h| {
@@ -472,20 +1052,43 @@ impl GameState {
         
         let mut total_flux = 0.;
         for harvester in &mut self.level_manager.loaded_level.harvesters {
-            total_flux += harvester.calculate_flux(&mut self.level_manager.loaded_level.actor_manager, &self.level_manager.loaded_level.tilemap.flux_cores);
+            total_flux += harvester.calculate_flux(&mut self.level_manager.loaded_level.actor_manager, &self.level_manager.loaded_level.tilemap.flux_cores, self.level_manager.loaded_level.frame);
         }
         
-        self.hud.update(total_flux, self.level_manager.loaded_level.required_flux);
+        // On a shared screen the HUD warns about whichever player is closer to dying
+        let exposure = if is_local_coop {
+            self.local_player.exposure.get_value().max(self.local_player2.exposure.get_value())
+        } else {
+            self.local_player.exposure.get_value()
+        };
+        self.hud.update(total_flux, self.level_manager.loaded_level.required_flux, exposure);
 
-        //show_total_flux(total_flux, &Vector2::new(screen_center.0 as f32, screen_center.1 as f32));
+        //show_total_flux(total_flux, &screen_center);
         //show_debug_info(self.last_fpsu, &screen_center);
         
         self.hud.draw();
-        let net_flux_field = net_flux_field_at_point(&self.local_player.get_position(), &self.level_manager.loaded_level.tilemap.flux_cores).length();
-        draw_shader_distortion_parameter_pixel(net_flux_field);
-        if net_flux_field > DEATH_THRESHOLD {
-            self.restart_level();
-            self.degauss_shader_counter = DEGAUSS_FRAMES;
+        if let Some(playback) = &self.replay_playback {
+            playback.draw(&screen_center);
+        }
+        if let Some(time_attack) = &self.time_attack {
+            time_attack.draw_timer(&screen_center);
+        }
+        if let Some(speedrun) = &self.speedrun {
+            speedrun.draw(&screen_center);
+        }
+        self.rewind.draw(&self.level_manager.loaded_level.rewind_settings, &screen_center);
+        draw_connection_overlay(&self.connection_status, &screen_center);
+        if self.debug_menu.is_open {
+            self.debug_menu.draw(&self.level_manager.loaded_level.movement_profile.name);
+        }
+        let net_flux_field = net_flux_field_at_point(&self.local_player.get_position(), &self.level_manager.loaded_level.tilemap.flux_cores, self.level_manager.loaded_level.frame).length();
+        // Distortion ramps up with exposure so the player gets a warning before dying
+        let distortion = net_flux_field + exposure * EXPOSURE_MAX_DISTORTION;
+        draw_shader_distortion_parameter_pixel(distortion.min(255.));
+        let is_local_player_dead = self.local_player.exposure.is_lethal() || self.level_manager.loaded_level.has_fallen_out(&self.local_player);
+        // The newest rewind frames can still be lethal, death waits until the button is released
+        if is_local_player_dead && self.death_timer == 0 && !is_rewinding && matches!(self.game_flow_state, GameFlowState::InGameSingle) {
+            self.start_death_sequence();
         }
         
         if !audio::is_playing("bg-music-nothing") {
@@ -493,41 +1096,244 @@ impl GameState {
         }
 
         if matches!(self.game_flow_state, GameFlowState::InGameSingle) {
-            if !self.level_manager.loaded_level.tilemap.is_inside(&self.local_player.get_position()) {
+            if let Some(exit_target) = self.level_manager.loaded_level.get_triggered_exit(&[self.local_player.get_position()]) {
                 log!("Completed level");
-                self.load_next_level();
-                match self.level_manager.current_level {
-                    Some(_) => { },
-                    None => {
-                        log!("Completed game");
-                        self.game_flow_state = GameFlowState::Credits;
-                    }
+                if self.replay_playback.is_some() {
+                    self.finish_replay();
+                    return;
+                }
+                self.finish_recording();
+                if self.time_attack.is_some() {
+                    self.finish_time_attack();
+                    return;
                 }
+                self.take_exit(exit_target);
+            }
+        } else if is_local_coop {
+            let player_positions = [self.local_player.get_position(), self.local_player2.get_position()];
+            if let Some(exit_target) = self.level_manager.loaded_level.get_triggered_exit(&player_positions) {
+                log!("Completed level");
+                self.take_exit(exit_target);
             }
         }
     }
     
+    // In co-op the server decides the profile so both players keep simulating the same physics
+    fn set_movement_profile(&mut self, profile_name: String) {
+        if matches!(self.game_flow_state, GameFlowState::InGameCoOp) {
+            if let Some(conn) = FluxGameStateChannel::subscribe(&self.room_code) {
+                let _ = conn.send(&ClientMsg::SetMovementProfile { profile_name });
+            }
+        } else {
+            self.level_manager.loaded_level.movement_profile = get_movement_profile(&profile_name);
+            // The recording only knows the profile the level started with
+            self.replay_recorder = None;
+        }
+    }
+
     fn reload_game(&mut self) {
         let level_manager =  LevelManager::new();
-        let local_player_position = level_manager.loaded_level.player1_start_position.clone();
+        let local_player_position = level_manager.loaded_level.get_player_start_position(0);
+        let local_player2_position = level_manager.loaded_level.local_coop_start_positions[1];
         self.level_manager = level_manager;
         self.local_player = Player::new(local_player_position.x, local_player_position.y);
+        self.local_player2 = Player::new(local_player2_position.x, local_player2_position.y);
+        self.camera = Camera::new(local_player_position);
+        self.death_timer = 0;
+        self.rewind = RewindBuffer::new();
     }
     
-    fn restart_level(&mut self) {
-        let local_player_position = self.level_manager.loaded_level.player1_start_position.clone();
-        self.local_player = Player::new(local_player_position.x, local_player_position.y);
-        self.level_manager.reload_current_level();
+    fn start_death_sequence(&mut self) {
+        if let Some(speedrun) = &mut self.speedrun {
+            // Without a checkpoint the level starts over
+            speedrun.add_death(self.level_manager.loaded_level.checkpoint_snapshot.is_none());
+        }
+        self.local_player.generate_death_particles(&mut self.level_manager.loaded_level.juice_particle_manager);
+        self.death_timer = DEATH_FRAMES;
+        self.degauss_shader_counter = DEGAUSS_FRAMES;
+        self.camera.add_trauma(DEATH_TRAUMA);
+    }
+
+    // Same rules as the co-op server: each player respawns on their own without pausing the other
+    fn handle_local_coop_deaths(&mut self) {
+        let level = &mut self.level_manager.loaded_level;
+        let mut respawned = [false; 2];
+        if self.local_player.exposure.is_lethal() || level.has_fallen_out(&self.local_player) {
+            self.local_player.generate_death_particles(&mut level.juice_particle_manager);
+            let start_position = level.local_coop_start_positions[0];
+            respawn_coop_player(&mut [&mut self.local_player, &mut self.local_player2], 0, level, &start_position);
+            respawned[0] = true;
+            self.degauss_shader_counter = DEGAUSS_FRAMES;
+            self.camera.add_trauma(DEATH_TRAUMA);
+        }
+        if self.local_player2.exposure.is_lethal() || level.has_fallen_out(&self.local_player2) {
+            self.local_player2.generate_death_particles(&mut level.juice_particle_manager);
+            let start_position = level.local_coop_start_positions[1];
+            respawn_coop_player(&mut [&mut self.local_player, &mut self.local_player2], 1, level, &start_position);
+            respawned[1] = true;
+            self.degauss_shader_counter = DEGAUSS_FRAMES;
+            self.camera.add_trauma(DEATH_TRAUMA);
+        }
+
+        // Nobody can be left off screen, so a checkpoint out of frame gives way to the partner
+        let (position, partner_position) = (self.local_player.get_position(), self.local_player2.get_position());
+        if !is_within_leash(&position, &partner_position) {
+            match respawned {
+                [true, false] => self.local_player.shift(&(partner_position - position)),
+                [false, true] => self.local_player2.shift(&(position - partner_position)),
+                _ => {},
+            }
+        }
+    }
+
+    fn respawn_local_player(&mut self) {
+        respawn_single_player(&mut self.local_player, &mut self.level_manager);
+        self.rewind.clear();
+    }
+
+    // Returns true while the rewind button holds the simulation. Rewinding during the death
+    // sequence takes the death back, so a mistake doesn't have to cost a restart.
+    fn update_rewind(&mut self) -> bool {
+        if !self.input_map.pressed(Action::Rewind) {
+            self.rewind.stop();
+            return false;
+        }
+        if !self.rewind.start(&self.level_manager.loaded_level.rewind_settings) {
+            return false;
+        }
+        self.death_timer = 0;
+        // The recording can't follow a run that went back in time
+        self.replay_recorder = None;
+        if self.degauss_shader_counter < DEGAUSS_FRAMES - REWIND_DEGAUSS_FRAMES {
+            self.degauss_shader_counter = DEGAUSS_FRAMES;
+        }
+        true
+    }
+
+    // Replays only cover single-player levels
+    fn start_recording(&mut self) {
+        self.replay_recorder = self.level_manager.current_level.clone()
+            .map(|level_name| Recording::new(level_name, self.level_manager.loaded_level.movement_profile.name.clone()));
+    }
+
+    fn finish_recording(&mut self) {
+        if let Some(mut recording) = self.replay_recorder.take() {
+            recording.outcome = Some(ReplayOutcome::capture(&self.local_player, &self.level_manager.loaded_level));
+            SaveData::save_replay(recording);
+        }
+    }
+
+    // A single level from its start, raced against the stored personal best
+    fn start_time_attack(&mut self, level_name: LevelName) {
+        let best = SaveData::load().get_time_attack_best(&level_name).cloned();
+        self.level_manager.load_level(level_name.clone());
+        self.reset_level_state();
+        self.replay_playback = None;
+        self.time_attack = Some(TimeAttack::new(level_name, best));
+        self.game_flow_state = GameFlowState::InGameSingle;
+        self.start_recording();
+    }
+
+    fn finish_time_attack(&mut self) {
+        if let Some(time_attack) = self.time_attack.take() {
+            let results = time_attack.finish();
+            if results.is_new_best {
+                SaveData::save_time_attack_best(results.run.clone());
+            }
+            self.time_attack_results = Some(results);
+        }
+        self.game_flow_state = GameFlowState::TimeAttackResults;
+    }
+
+    fn start_speedrun(&mut self) {
+        self.speedrun = self.level_manager.current_level.clone()
+            .map(|level_name| SpeedrunTimer::new(level_name, SaveData::load().speedrun_best));
+    }
+
+    // Abandoned runs are kept in the history as well, only completed ones can become the best
+    fn finish_speedrun(&mut self, is_completed: bool) {
+        if let Some(speedrun) = self.speedrun.take() {
+            SaveData::save_speedrun(speedrun.finish(is_completed));
+        }
+    }
+
+    fn start_replay(&mut self, recording: Recording) {
+        self.level_manager.load_level(recording.level_name.clone());
+        self.reset_level_state();
+        self.level_manager.loaded_level.movement_profile = get_movement_profile(&recording.movement_profile);
+        self.replay_recorder = None;
+        self.time_attack = None;
+        self.replay_playback = Some(ReplayPlayback::new(recording));
+        self.game_flow_state = GameFlowState::InGameSingle;
+    }
+
+    // Playing the inputs back has to end exactly where the recorded run did
+    fn finish_replay(&mut self) {
+        if let Some(playback) = self.replay_playback.take() {
+            let outcome = ReplayOutcome::capture(&self.local_player, &self.level_manager.loaded_level);
+            match &playback.recording.outcome {
+                Some(recorded_outcome) if recorded_outcome.matches(&outcome) => log!("Replay matched the recorded run"),
+                _ => log!("Replay diverged from the recorded run"),
+            }
+        }
+        self.game_flow_state = GameFlowState::Replays;
+    }
+
+    fn take_exit(&mut self, exit_target: ExitTarget) {
+        match exit_target {
+            ExitTarget::NextLevel => {
+                self.load_next_level();
+                match self.level_manager.current_level.clone() {
+                    Some(level_name) => {
+                        if matches!(self.game_flow_state, GameFlowState::InGameSingle) {
+                            self.start_recording();
+                        }
+                        if let Some(speedrun) = &mut self.speedrun {
+                            speedrun.complete_level(level_name);
+                        }
+                    },
+                    None => {
+                        log!("Completed game");
+                        self.finish_speedrun(true);
+                        self.game_flow_state = GameFlowState::Credits;
+                    }
+                }
+            },
+            ExitTarget::Level(level_name) => {
+                if let Some(speedrun) = &mut self.speedrun {
+                    speedrun.complete_level(level_name.clone());
+                }
+                self.level_manager.load_level(level_name);
+                self.reset_level_state();
+                if matches!(self.game_flow_state, GameFlowState::InGameSingle) {
+                    self.start_recording();
+                }
+            },
+            ExitTarget::Hub => {
+                self.finish_speedrun(false);
+                self.game_flow_state = GameFlowState::MainMenu;
+            },
+        }
     }
 
     fn load_next_level(&mut self) {
         self.level_manager.load_next_level();
-        let local_player_start_position = self.level_manager.loaded_level.player1_start_position.clone();
+        self.reset_level_state();
+    }
+
+    fn reset_level_state(&mut self) {
+        let local_player_start_position = self.level_manager.loaded_level.get_player_start_position(0);
         self.local_player = Player::new_with_id(self.local_player.id.clone(), local_player_start_position.x, local_player_start_position.y);
+        let local_player2_start_position = self.level_manager.loaded_level.local_coop_start_positions[1];
+        self.local_player2 = Player::new(local_player2_start_position.x, local_player2_start_position.y);
+        self.camera = Camera::new(local_player_start_position);
         self.server_player_position = Vector2::zero();
-        self.unprocessed_local_inputs = VecDeque::new();
-        self.remote_player_snapshots = VecDeque::new();
-        self.particle_manager = ParticleManager::new(self.level_manager.loaded_level.tilemap.flux_cores.clone());
+        self.prediction_buffer = PredictionBuffer::new(PREDICTION_BUFFER_SIZE);
+        self.remote_player_interpolators = BTreeMap::new();
+        self.snapshot_history = SnapshotHistory::new();
+        self.death_timer = 0;
+        self.rewind = RewindBuffer::new();
+        self.particle_manager = ParticleManager::new(self.level_manager.loaded_level.tilemap.flux_cores.clone(), self.level_manager.loaded_level.exposure_settings.threshold);
     }
 
     // This is needed for the degauss shader
@@ -547,16 +1353,6 @@ impl GameState {
 
 }
 
-fn smooth_position(position_snapshots: Vec<Vector2>) -> Vector2 {
-    let n = position_snapshots.len();
-    let mut sum = Vector2::zero();
-    for snapshot in position_snapshots {
-        sum = sum + snapshot; 
-    }
-    
-    sum * (1. / n as f32)
-}
-
 fn show_debug_info(fpsu: u32, screen_center: &Vector2) {
     let mut a = "fpsu: ".to_owned();
     a.push_str(&(fpsu.to_string()));
@@ -578,9 +1374,8 @@ pub struct UserInput {
     right_pressed: bool,
     up_pressed: bool,
     down_pressed: bool,
-    a_just_pressed: bool,
-    x_just_pressed: bool,
-    y_just_pressed: bool,
+    pick_just_pressed: bool,
+    dash_just_pressed: bool,
 }
 
 impl UserInput {
@@ -593,19 +1388,35 @@ impl UserInput {
             right_pressed: false,
             up_pressed: false,
             down_pressed: false,
-            a_just_pressed: false,
-            x_just_pressed: false,
-            y_just_pressed: false,
+            pick_just_pressed: false,
+            dash_just_pressed: false,
+        }
+    }
+
+    // Same buttons held down, without the presses that should only happen once
+    pub fn get_held(&self) -> Self {
+        Self {
+            jump_just_pressed: false,
+            pick_just_pressed: false,
+            dash_just_pressed: false,
+            ..self.clone()
         }
     }
 }
  
 #[turbo::serialize]
 pub enum ClientMsg {
+    // The newest inputs oldest first, repeated until the server acknowledges them
     UserInput {
-        user_input: UserInput,
+        user_inputs: Vec<UserInput>,
+        // Newest snapshot the client decoded, the server diffs against it
+        acknowledged_snapshot: Option<u32>,
+    },
+    Ready,
+    // Hot-swap of the level movement profile from the debug menu
+    SetMovementProfile {
+        profile_name: String,
     },
-    Ready
 }
 
 #[turbo::serialize]
@@ -616,28 +1427,57 @@ pub enum ServerMsg {
     ConnectionSuccessful {
         player_id: String,
     }, 
+    // Who is in the room before the game starts
+    LobbyState {
+        player_count: u32,
+        ready_count: u32,
+    },
+    // Sent to each client separately, since the delta depends on what that client acknowledged
     GameState {
-        player1: Player,
-        last_processed_tick_p1: Option<usize>,
-        player2: Player,
-        last_processed_tick_p2: Option<usize>,
-        harvesters: Vec<Harvester>,
-        actor_manager: ActorManager,
+        snapshot: SnapshotDelta,
+        last_processed_tick: Option<usize>,
+        // Every CHECKSUM_INTERVAL_TICKS, for spotting desyncs between prediction and server
+        checksum: Option<StateChecksum>,
+    },
+    // Signal that a player died and was respawned at the last checkpoint
+    PlayerDied {
+        player_id: String,
+    },
+    LevelCompleted {
+        exit_target: ExitTarget,
+    },
+    MovementProfileChanged {
+        profile_name: String,
     },
-    LevelCompleted,
     GameCompleted,
+    // The server pauses the game until the player reconnects or their slot is released
+    PlayerDisconnected {
+        player_id: String,
+    },
+    PlayerReconnected {
+        player_id: String,
+    },
+    PlayerLeft {
+        player_id: String,
+    },
+    // Sent to a reconnecting player, the full snapshot follows with the next tick
+    Resync {
+        level_name: Option<LevelName>,
+        profile_name: String,
+    },
 }
 
 #[turbo::os::channel(program = "testchannel4", name = "main")] 
 pub struct FluxGameStateChannel {
     level_manager: LevelManager,
-    player1: Player,
-    player1_inputs: VecDeque<UserInput>,
-    player1_ready: bool,
-    player2: Player,
-    player2_inputs: VecDeque<UserInput>,
-    player2_ready: bool,
+    // Keyed by user id, ordered so every tick simulates the players in the same order
+    players: BTreeMap<String, PlayerSlot>,
     game_started: bool,
+    server_tick: u32,
+    // Time not yet simulated, frames run at the client rate regardless of the server interval
+    frame_accumulator_ms: f32,
+    snapshot_history: SnapshotHistory,
+    bandwidth_meter: BandwidthMeter,
 }
 
 impl ChannelHandler for FluxGameStateChannel { 
@@ -645,141 +1485,308 @@ impl ChannelHandler for FluxGameStateChannel {
     type Send = ServerMsg; // outgoing to client
                              //
     fn new() -> Self { 
-        let level = construct_level_1();
-        let player1_start_position = level.player1_start_position.clone();
-        let player2_start_position = level.player2_start_position.clone();
         Self {
             level_manager: LevelManager::new(),
-            player1: Player::new(player1_start_position.x, player1_start_position.y),
-            player1_inputs: VecDeque::new(),
-            player1_ready: false,
-            player2: Player::new(player2_start_position.x, player2_start_position.y),
-            player2_inputs: VecDeque::new(),
-            player2_ready: false,
+            players: BTreeMap::new(),
             game_started: false,
+            server_tick: 0,
+            frame_accumulator_ms: 0.,
+            snapshot_history: SnapshotHistory::new(),
+            bandwidth_meter: BandwidthMeter::new(),
         }
     } 
     
     fn on_open(&mut self, settings: &mut ChannelSettings) -> Result<(), std::io::Error> {
-        settings.set_interval(20);
+        settings.set_interval(SERVER_TICK_MS);
         Result::Ok(())
     }
     
     fn on_connect(&mut self, user_id: &str) -> Result<(), std::io::Error> {
-        let mut connect_successful = false;
+        if self.players.contains_key(user_id) {
+            return self.reconnect_player(user_id);
+        }
+        if self.game_started {
+            return Result::Err(Error::other("Game already started"));
+        }
+
         // If the user is not already registered in the channel, register them
-        if self.player1.id.is_empty() && self.player2.id != user_id {
-            self.player1.id = user_id.to_string();
-            connect_successful = true;
-        } else if self.player2.id.is_empty() {
-            self.player2.id = user_id.to_string();
-            connect_successful = true;
+        match get_free_slot_index(&self.players) {
+            Some(index) => {
+                let start_position = self.level_manager.loaded_level.get_player_start_position(index);
+                self.players.insert(user_id.to_string(), PlayerSlot::new(index, user_id, start_position));
+                broadcast_to_clients(ServerMsg::ConnectionSuccessful { player_id: user_id.to_string() })?;
+                self.broadcast_lobby_state()
+            },
+            None => Result::Err(Error::other("Lobby full :(")),
+        }
+    }
+    
+    fn on_disconnect(&mut self, user_id: &str) -> Result<(), std::io::Error> {
+        if !self.players.contains_key(user_id) {
+            return Result::Ok(());
         }
 
-        if connect_successful {
-            return os::server::channel::broadcast(ServerMsg::ConnectionSuccessful  { player_id: user_id.to_string() });
-        } else {
-            Result::Err(Error::new(ErrorKind::Other ,"Lobby full :("))
+        // Nothing to hold on to before the game started
+        if !self.game_started {
+            return self.release_slot(user_id);
         }
+        if let Some(slot) = self.players.get_mut(user_id) {
+            slot.disconnect_timer = Some(RECONNECT_GRACE_TICKS);
+        }
+        broadcast_to_clients(ServerMsg::PlayerDisconnected { player_id: user_id.to_string() })
     }
     
     fn on_interval(&mut self) -> Result<(), std::io::Error> {
         if !self.game_started {
             return Result::Ok(());
         }
-        let mut last_processed_tick_p1: Option<usize> = None;
-        let mut last_processed_tick_p2: Option<usize> = None;
+        self.server_tick += 1;
+
+        let expired_ids: Vec<String> = self.players.iter()
+            .filter(|(_, slot)| slot.disconnect_timer == Some(0))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &expired_ids {
+            self.release_slot(id)?;
+        }
+        if !self.game_started {
+            return Result::Ok(());
+        }
+
+        // The game is on hold while someone is missing, so inputs sent meanwhile are dropped
+        let is_paused = self.players.values().any(|slot| !slot.is_connected());
+        for slot in self.players.values_mut() {
+            slot.disconnect_timer = slot.disconnect_timer.map(|timer| timer - 1);
+            slot.last_processed_tick = None;
+            if is_paused {
+                slot.input_buffer.clear();
+            }
+        }
+
+        // Steady pacing: as many frames as the clients played in this interval, plus one
+        // to catch up when a player's buffer grew too deep after a burst of packets
+        let mut frame_count = 0;
+        if !is_paused {
+            self.frame_accumulator_ms += SERVER_TICK_MS as f32;
+            while self.frame_accumulator_ms >= CLIENT_FRAME_MS {
+                self.frame_accumulator_ms -= CLIENT_FRAME_MS;
+                frame_count += 1;
+            }
+            if self.players.values().any(|slot| slot.input_buffer.is_overfilled()) {
+                frame_count += 1;
+            }
+        }
         
-        while !self.player1_inputs.is_empty() || !self.player2_inputs.is_empty() {
-            let player1_input = self.player1_inputs.pop_front();
-            let player2_input = self.player2_inputs.pop_front();
-            
-            match (player1_input, player2_input) {
-                (Some(input1), Some(input2)) => {
-                    last_processed_tick_p1 = Some(input1.tick);
-                    last_processed_tick_p2 = Some(input2.tick);
-                    simulate_server_frame(&mut self.player1, &input1, &mut self.player2, &input2, &mut self.level_manager.loaded_level);
-                },
-                (Some(input1), None) => {
-                    last_processed_tick_p1 = Some(input1.tick);
-                    simulate_server_frame(&mut self.player1, &input1, &mut self.player2, &UserInput::new(), &mut self.level_manager.loaded_level);
-                },
-                (None, Some(input2)) => {
-                    last_processed_tick_p2 = Some(input2.tick);
-                    simulate_server_frame(&mut self.player1, &UserInput::new(), &mut self.player2, &input2, &mut self.level_manager.loaded_level);
-                },
-                (None, None) => {}
+        // Players whose buffer is refilling keep holding their last buttons for that frame
+        let mut is_last_input_real = vec![false; self.players.len()];
+        for _ in 0..frame_count {
+            let mut inputs = vec![];
+            for (idx, slot) in self.players.values_mut().enumerate() {
+                is_last_input_real[idx] = match slot.input_buffer.pop() {
+                    Some(input) => {
+                        slot.last_processed_tick = Some(input.tick);
+                        inputs.push(input);
+                        true
+                    },
+                    None => {
+                        inputs.push(slot.input_buffer.get_held_input());
+                        false
+                    },
+                };
+            }
+            let mut players: Vec<&mut Player> = self.players.values_mut().map(|slot| &mut slot.player).collect();
+            simulate_server_frame(&mut players, &inputs, &mut self.level_manager.loaded_level);
+        }
+
+        // Hashed before deaths are handled, that's the state the client predicted for its last input.
+        // Skipped when held input ran after it, the client never predicted that state.
+        let mut checksums: BTreeMap<String, StateChecksum> = BTreeMap::new();
+        if self.server_tick.is_multiple_of(CHECKSUM_INTERVAL_TICKS) {
+            let level = &self.level_manager.loaded_level;
+            for ((id, slot), is_real) in self.players.iter().zip(&is_last_input_real) {
+                if *is_real && slot.last_processed_tick.is_some() {
+                    checksums.insert(id.clone(), StateChecksum::capture(level.frame, &slot.player, &level.actor_manager));
+                }
             }
         }
         
-        if !self.level_manager.loaded_level.tilemap.is_inside(&self.player1.get_position()) && !self.level_manager.loaded_level.tilemap.is_inside(&self.player2.get_position()) {
-            self.level_manager.load_next_level();
+        let level = &mut self.level_manager.loaded_level;
+        let dead_indices: Vec<usize> = self.players.values()
+            .enumerate()
+            .filter(|(_, slot)| slot.player.exposure.is_lethal() || level.has_fallen_out(&slot.player))
+            .map(|(idx, _)| idx)
+            .collect();
+        for idx in dead_indices {
+            let slot_index = self.players.values().nth(idx).map_or(0, |slot| slot.index);
+            let start_position = level.get_player_start_position(slot_index);
+            let mut players: Vec<&mut Player> = self.players.values_mut().map(|slot| &mut slot.player).collect();
+            respawn_coop_player(&mut players, idx, level, &start_position);
+            broadcast_to_clients(ServerMsg::PlayerDied { player_id: players[idx].id.clone() })?;
+        }
+
+        let player_positions: Vec<Vector2> = self.players.values().map(|slot| slot.player.get_position()).collect();
+        if let Some(exit_target) = self.level_manager.loaded_level.get_triggered_exit(&player_positions) {
+            match &exit_target {
+                ExitTarget::NextLevel => self.level_manager.load_next_level(),
+                ExitTarget::Level(level_name) => self.level_manager.load_level(level_name.clone()),
+                ExitTarget::Hub => return self.end_game(ServerMsg::LevelCompleted { exit_target }),
+            }
+            // Snapshots of the previous level are useless as baselines
+            self.snapshot_history = SnapshotHistory::new();
             match self.level_manager.current_level {
                 Some(_) => {
-                    let p1_start = self.level_manager.loaded_level.player1_start_position.clone();
-                    self.player1 = Player::new_with_id(self.player1.id.clone(), p1_start.x, p1_start.y);
-
-                    let p2_start = self.level_manager.loaded_level.player2_start_position.clone();
-                    self.player2 = Player::new_with_id(self.player2.id.clone(), p2_start.x, p2_start.y);
-                    return os::server::channel::broadcast(ServerMsg::LevelCompleted);
+                    for (id, slot) in self.players.iter_mut() {
+                        let start_position = self.level_manager.loaded_level.get_player_start_position(slot.index);
+                        slot.player = Player::new_with_id(id.clone(), start_position.x, start_position.y);
+                    }
+                    return broadcast_to_clients(ServerMsg::LevelCompleted { exit_target });
                 },
                 None => {
-                    return os::server::channel::broadcast(ServerMsg::GameCompleted);
+                    return self.end_game(ServerMsg::GameCompleted);
                 }
             }
         }
 
-        return os::server::channel::broadcast(
-            ServerMsg::GameState {
-                harvesters: self.level_manager.loaded_level.harvesters.clone(),
-                actor_manager: self.level_manager.loaded_level.actor_manager.clone(),
-                player1: self.player1.clone(),
-                last_processed_tick_p1,
-                player2: self.player2.clone(),
-                last_processed_tick_p2
+        let players = self.players.iter().map(|(id, slot)| (id.clone(), slot.player.clone())).collect();
+        let snapshot = WorldSnapshot::capture(self.server_tick, players, &self.level_manager.loaded_level);
+        let full_state_bytes = borsh::to_vec(&ServerMsg::GameState {
+            snapshot: SnapshotDelta::Full(snapshot.clone()),
+            last_processed_tick: None,
+            checksum: None,
+        }).map_or(0, |bytes| bytes.len());
+        for (user_id, slot) in &self.players {
+            if !slot.is_connected() {
+                continue;
             }
-        );
+            let baseline = slot.acknowledged_snapshot.and_then(|tick| self.snapshot_history.get(tick));
+            let msg = ServerMsg::GameState {
+                snapshot: snapshot.diff(baseline),
+                last_processed_tick: slot.last_processed_tick,
+                checksum: checksums.remove(user_id),
+            };
+            self.bandwidth_meter.record(borsh::to_vec(&msg).map_or(0, |bytes| bytes.len()), full_state_bytes);
+            send_to_client(user_id, msg)?;
+        }
+        self.snapshot_history.push(snapshot);
+        self.bandwidth_meter.tick(SERVER_TICK_MS);
+
+        Result::Ok(())
     }
 
     fn on_data(&mut self, user_id: &str, data: Self::Recv) -> Result<(), std::io::Error> { 
         match data {
-            ClientMsg::UserInput { user_input } => {
-                if user_id == self.player1.id {
-                    self.player1_inputs.push_back(user_input);
-                } else if user_id == self.player2.id {
-                    self.player2_inputs.push_back(user_input);
+            ClientMsg::UserInput { user_inputs, acknowledged_snapshot } => {
+                if let Some(slot) = self.players.get_mut(user_id) {
+                    // Redundant copies of inputs the buffer already has are dropped by tick
+                    for user_input in user_inputs {
+                        slot.input_buffer.push(user_input);
+                    }
+                    // Acks can arrive out of order, only ever move the baseline forward
+                    slot.acknowledged_snapshot = slot.acknowledged_snapshot.max(acknowledged_snapshot);
                 }
             },
             ClientMsg::Ready => {
-                if user_id == self.player1.id {
-                    self.player1_ready = true;                    
-                } else if user_id == self.player2.id {
-                    self.player2_ready = true;                    
+                if let Some(slot) = self.players.get_mut(user_id) {
+                    slot.is_ready = true;
                 }
                 
-                if self.player1_ready && self.player2_ready {
+                // Everyone in the room has to be ready, and co-op needs at least two players
+                if self.players.len() >= 2 && self.players.values().all(|slot| slot.is_ready) {
                     self.game_started = true;
-                    return os::server::channel::broadcast(ServerMsg::StartGame);
+                    return broadcast_to_clients(ServerMsg::StartGame);
                 }
-            }
+                return self.broadcast_lobby_state();
+            },
+            ClientMsg::SetMovementProfile { profile_name } => {
+                self.level_manager.loaded_level.movement_profile = get_movement_profile(&profile_name);
+                return broadcast_to_clients(ServerMsg::MovementProfileChanged { profile_name });
+            },
         }
         Result::Ok(())
     } 
 } 
 
+impl FluxGameStateChannel {
+    fn broadcast_lobby_state(&self) -> Result<(), std::io::Error> {
+        broadcast_to_clients(ServerMsg::LobbyState {
+            player_count: self.players.len() as u32,
+            ready_count: self.players.values().filter(|slot| slot.is_ready).count() as u32,
+        })
+    }
+
+    // Hands the slot back and resends everything the client needs to pick up where it left off
+    fn reconnect_player(&mut self, user_id: &str) -> Result<(), std::io::Error> {
+        if let Some(slot) = self.players.get_mut(user_id) {
+            slot.disconnect_timer = None;
+            slot.acknowledged_snapshot = None;
+        }
+
+        send_to_client(user_id, ServerMsg::ConnectionSuccessful { player_id: user_id.to_string() })?;
+        send_to_client(user_id, ServerMsg::Resync {
+            level_name: self.level_manager.current_level.clone(),
+            profile_name: self.level_manager.loaded_level.movement_profile.name.clone(),
+        })?;
+        broadcast_to_clients(ServerMsg::PlayerReconnected { player_id: user_id.to_string() })
+    }
+
+    // Everyone goes back to the menus after the last level or through the hub, so the room
+    // starts over empty with every slot free
+    fn end_game(&mut self, msg: ServerMsg) -> Result<(), std::io::Error> {
+        *self = Self::new();
+        broadcast_to_clients(msg)
+    }
+
+    // Frees the slot, the others keep playing as long as two are left. New players can only
+    // join before the game started, so with one left the room goes back to the lobby and
+    // everyone has to ready up again once someone took the slot.
+    fn release_slot(&mut self, user_id: &str) -> Result<(), std::io::Error> {
+        self.players.remove(user_id);
+        if self.players.is_empty() {
+            *self = Self::new();
+            return Result::Ok(());
+        }
+
+        if !self.game_started {
+            return self.broadcast_lobby_state();
+        }
+        broadcast_to_clients(ServerMsg::PlayerLeft { player_id: user_id.to_string() })?;
+        if self.players.len() < 2 {
+            self.game_started = false;
+            for slot in self.players.values_mut() {
+                slot.is_ready = false;
+            }
+            return self.broadcast_lobby_state();
+        }
+        Result::Ok(())
+    }
+}
+
 fn simulate_frame(player: &mut Player, level: &mut Level, input: &UserInput) {
     let Level {
         tilemap,
         harvesters,
         actor_manager,
-        player1_start_position: _,
-        player2_start_position: _,
+        player_start_positions: _,
+        local_coop_start_positions: _,
+        checkpoints: _,
+        checkpoint_snapshot: _,
+        exits: _,
         background: _,
         required_flux,
+        exposure_settings,
+        movement_profile,
+        movement_zones,
+        camera_zones: _,
+        rewind_settings: _,
         juice_particle_manager,
+        frame,
     } = level;
+    // Advanced first, so flux evaluated after the frame (like the HUD does) sees the same clock
+    *frame += 1;
+    let frame = *frame;
 
-    let mut solids: Vec<&Solid> = vec![];
+    let edge_walls = tilemap.get_edge_walls();
+    let mut solids: Vec<&Solid> = edge_walls.iter().collect();
     for tile in &tilemap.tiles {
         solids.push(&tile.solid);        
     }
@@ -793,8 +1800,10 @@ fn simulate_frame(player: &mut Player, level: &mut Level, input: &UserInput) {
         }
     }
         
-    let flux_field_at_player = net_flux_field_at_point(&player.actor.position, &tilemap.flux_cores);
-    player.handle_input(actor_manager, juice_particle_manager, input, flux_field_at_player);
+    let flux_field_at_player = net_flux_field_at_point(&player.actor.position, &tilemap.flux_cores, frame);
+    let player_movement_profile = get_movement_profile_at(&player.get_position(), movement_profile, movement_zones);
+    player.exposure.update(flux_field_at_player.length(), exposure_settings);
+    player.handle_input(actor_manager, juice_particle_manager, input, flux_field_at_player, player_movement_profile);
 
     // Add gravity to 
     for harvester in harvesters.iter_mut() {
@@ -803,14 +1812,14 @@ fn simulate_frame(player: &mut Player, level: &mut Level, input: &UserInput) {
 
     player.pick_item(&mut level.actor_manager);
     // Move player
-    player.actor_move(&solids, &mut level.actor_manager);
+    player.actor_move(&solids, &mut level.actor_manager, player_movement_profile);
 
     // Move harvesters
     level.harvesters.iter_mut().for_each(|h| h.actor_move(&solids, &mut level.actor_manager));
 
     let mut total_flux = 0.;
     for harvester in &mut level.harvesters {
-        total_flux += harvester.calculate_flux(&mut level.actor_manager, &level.tilemap.flux_cores);
+        total_flux += harvester.calculate_flux(&mut level.actor_manager, &level.tilemap.flux_cores, frame);
     }
     
     for door in &mut level.tilemap.doors {
@@ -818,21 +1827,68 @@ fn simulate_frame(player: &mut Player, level: &mut Level, input: &UserInput) {
             door.open = total_flux >= *required_flux;
         }
     }
+
+    level.update_checkpoints(&player.actor.get_bound());
+}
+
+// Respawns players[index], restoring the checkpoint resets the carried items of everyone else
+fn respawn_coop_player(players: &mut [&mut Player], index: usize, level: &mut Level, start_position: &Vector2) {
+    let respawn_position = match level.restore_checkpoint() {
+        Some(checkpoint_position) => {
+            for (idx, partner) in players.iter_mut().enumerate() {
+                if idx != index {
+                    partner.drop_item();
+                }
+            }
+            checkpoint_position
+        },
+        None => *start_position,
+    };
+    let player = &mut players[index];
+    **player = Player::new_with_id(player.id.clone(), respawn_position.x, respawn_position.y);
+}
+
+// Back to the last reached checkpoint, or the whole level starts over when there is none.
+// Replays go through this too, so it has to stay free of anything outside the simulation.
+fn respawn_single_player(player: &mut Player, level_manager: &mut LevelManager) {
+    let respawn_position = match level_manager.loaded_level.restore_checkpoint() {
+        Some(checkpoint_position) => checkpoint_position,
+        None => {
+            let start_position = level_manager.loaded_level.get_player_start_position(0);
+            level_manager.reload_current_level();
+            start_position
+        },
+    };
+    *player = Player::new_with_id(player.id.clone(), respawn_position.x, respawn_position.y);
 }
 
-fn simulate_server_frame(player1: &mut Player, input1: &UserInput, player2: &mut Player, input2: &UserInput, level: &mut Level) {
+// Players and inputs are matched by index, every player gets exactly one input per frame
+fn simulate_server_frame(players: &mut [&mut Player], inputs: &[UserInput], level: &mut Level) {
     let Level {
         tilemap,
         harvesters,
         actor_manager,
-        player1_start_position: _,
-        player2_start_position: _,
+        player_start_positions: _,
+        local_coop_start_positions: _,
+        checkpoints: _,
+        checkpoint_snapshot: _,
+        exits: _,
         background: _,
         required_flux,
+        exposure_settings,
+        movement_profile,
+        movement_zones,
+        camera_zones: _,
+        rewind_settings: _,
         juice_particle_manager,
+        frame,
     } = level;
+    // Advanced first, so flux evaluated after the frame (like the HUD does) sees the same clock
+    *frame += 1;
+    let frame = *frame;
 
-    let mut solids: Vec<&Solid> = vec![];
+    let edge_walls = tilemap.get_edge_walls();
+    let mut solids: Vec<&Solid> = edge_walls.iter().collect();
     for tile in &tilemap.tiles {
         solids.push(&tile.solid);        
     }
@@ -845,26 +1901,35 @@ fn simulate_server_frame(player1: &mut Player, input1: &UserInput, player2: &mut
         }
     }
         
-    player1.handle_input(actor_manager, juice_particle_manager, input1, Vector2::zero());
-    player2.handle_input(actor_manager, juice_particle_manager, input2, Vector2::zero());
+    let movement_profiles: Vec<&MovementProfile> = players.iter()
+        .map(|player| get_movement_profile_at(&player.get_position(), movement_profile, movement_zones))
+        .collect();
+    // Same field evaluation as simulate_frame so dashes and exposure match client prediction
+    for ((player, input), player_movement_profile) in players.iter_mut().zip(inputs).zip(&movement_profiles) {
+        let flux_field_at_player = net_flux_field_at_point(&player.actor.position, &tilemap.flux_cores, frame);
+        player.exposure.update(flux_field_at_player.length(), exposure_settings);
+        player.handle_input(actor_manager, juice_particle_manager, input, flux_field_at_player, player_movement_profile);
+    }
 
     // Add gravity to 
     for harvester in harvesters.iter_mut() {
         harvester.apply_gravity(&mut level.actor_manager);
     }
 
-    player1.pick_item(&mut level.actor_manager);
-    player2.pick_item(&mut level.actor_manager);
+    for player in players.iter_mut() {
+        player.pick_item(&mut level.actor_manager);
+    }
     // Move player
-    player1.actor_move(&solids, &mut level.actor_manager);
-    player2.actor_move(&solids, &mut level.actor_manager);
+    for (player, player_movement_profile) in players.iter_mut().zip(&movement_profiles) {
+        player.actor_move(&solids, &mut level.actor_manager, player_movement_profile);
+    }
 
     // Move harvesters
     level.harvesters.iter_mut().for_each(|h| h.actor_move(&solids, &mut level.actor_manager));
 
     let mut total_flux = 0.;
     for harvester in &mut level.harvesters {
-        total_flux += harvester.calculate_flux(&mut level.actor_manager, &level.tilemap.flux_cores);
+        total_flux += harvester.calculate_flux(&mut level.actor_manager, &level.tilemap.flux_cores, frame);
     }
     
     for door in &mut level.tilemap.doors {
@@ -872,4 +1937,8 @@ fn simulate_server_frame(player1: &mut Player, input1: &UserInput, player2: &mut
             door.open = total_flux >= *required_flux;
         }
     }
+
+    for player in players.iter() {
+        level.update_checkpoints(&player.actor.get_bound());
+    }
 }
\ No newline at end of file
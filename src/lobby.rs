@@ -0,0 +1,144 @@
+use crate::*;
+
+const ROOM_CODE_LENGTH: usize = 4;
+// No I or O, they are too easy to mistake for 1 and 0 when read out loud
+pub const ROOM_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+// Every client talks to the same directory instance, each room then gets its own game channel
+pub const LOBBY_DIRECTORY_ID: &str = "directory";
+
+#[turbo::serialize]
+pub enum LobbyClientMsg {
+    CreateRoom,
+    JoinRoom {
+        code: String,
+    },
+    ListRooms,
+    // Sent by the host when backing out before anyone joined
+    CloseRoom {
+        code: String,
+    },
+}
+
+#[turbo::serialize]
+pub enum LobbyServerMsg {
+    Created {
+        code: String,
+    },
+    Joined {
+        code: String,
+    },
+    // The room doesn't exist or is already full
+    Unavailable {
+        code: String,
+    },
+    // Rooms that still have a free slot
+    OpenRooms {
+        codes: Vec<String>,
+    },
+}
+
+#[turbo::serialize]
+pub struct Room {
+    pub code: String,
+    pub host_id: String,
+    pub guest_ids: Vec<String>,
+}
+
+impl Room {
+    fn is_open(&self) -> bool {
+        self.guest_ids.len() + 1 < MAX_PLAYERS
+    }
+}
+
+#[turbo::os::channel(program = "testchannel4", name = "lobby")]
+pub struct LobbyChannel {
+    rooms: Vec<Room>,
+    code_seed: u32,
+}
+
+impl LobbyChannel {
+    // Xorshift is plenty for codes that only need to be short and unlikely to repeat
+    fn next_code_seed(&mut self) -> u32 {
+        let mut seed = self.code_seed;
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        self.code_seed = seed;
+        seed
+    }
+
+    fn generate_room_code(&mut self) -> String {
+        loop {
+            let mut seed = self.next_code_seed();
+            let mut code = String::new();
+            for _ in 0..ROOM_CODE_LENGTH {
+                code.push(ROOM_CODE_ALPHABET[seed as usize % ROOM_CODE_ALPHABET.len()] as char);
+                seed /= ROOM_CODE_ALPHABET.len() as u32;
+            }
+            if !self.rooms.iter().any(|room| room.code == code) {
+                return code;
+            }
+        }
+    }
+
+    fn get_open_room_codes(&self) -> Vec<String> {
+        self.rooms.iter().filter(|room| room.is_open()).map(|room| room.code.clone()).collect()
+    }
+}
+
+impl ChannelHandler for LobbyChannel {
+    type Recv = LobbyClientMsg;
+    type Send = LobbyServerMsg;
+
+    fn new() -> Self {
+        Self {
+            rooms: vec![],
+            // Xorshift never leaves zero, so make sure the seed isn't zero
+            code_seed: (time::now() as u32) | 1,
+        }
+    }
+
+    // Rooms die with their host, and a guest leaving before the game starts frees the slot again
+    fn on_disconnect(&mut self, user_id: &str) -> Result<(), std::io::Error> {
+        self.rooms.retain(|room| room.host_id != user_id);
+        for room in &mut self.rooms {
+            room.guest_ids.retain(|guest_id| guest_id != user_id);
+        }
+        Result::Ok(())
+    }
+
+    fn on_data(&mut self, user_id: &str, data: Self::Recv) -> Result<(), std::io::Error> {
+        match data {
+            LobbyClientMsg::CreateRoom => {
+                // A host only ever has one open room
+                self.rooms.retain(|room| room.host_id != user_id);
+                let code = self.generate_room_code();
+                self.rooms.push(Room {
+                    code: code.clone(),
+                    host_id: user_id.to_string(),
+                    guest_ids: vec![],
+                });
+                os::server::channel::send(user_id, LobbyServerMsg::Created { code })
+            },
+            LobbyClientMsg::JoinRoom { code } => {
+                let code = code.to_uppercase();
+                match self.rooms.iter_mut().find(|room| room.code == code && room.is_open() && room.host_id != user_id) {
+                    Some(room) => {
+                        if !room.guest_ids.iter().any(|guest_id| guest_id == user_id) {
+                            room.guest_ids.push(user_id.to_string());
+                        }
+                        os::server::channel::send(user_id, LobbyServerMsg::Joined { code })
+                    },
+                    None => os::server::channel::send(user_id, LobbyServerMsg::Unavailable { code }),
+                }
+            },
+            LobbyClientMsg::ListRooms => {
+                os::server::channel::send(user_id, LobbyServerMsg::OpenRooms { codes: self.get_open_room_codes() })
+            },
+            LobbyClientMsg::CloseRoom { code } => {
+                self.rooms.retain(|room| !(room.code == code && room.host_id == user_id));
+                Result::Ok(())
+            },
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use crate::*;
+
+const MENU_X: i32 = 63;
+const FIRST_OPTION_Y: i32 = 60;
+const OPTION_HEIGHT: i32 = 22;
+const MAX_LISTED_ROOMS: usize = 6;
+// Quick match asks the directory for open rooms about twice a second
+const ROOM_LIST_REFRESH_FRAMES: u32 = 30;
+const CODE_LENGTH: usize = 4;
+const SELECTED_COLOR: u32 = 0x4be5feff;
+
+#[turbo::serialize]
+#[derive(PartialEq)]
+pub enum LobbyScreen {
+    Options,
+    EnterCode,
+    QuickMatch,
+    // Waiting for the directory to answer a create or join request
+    Connecting,
+}
+
+// What the lobby wants the game to ask the directory for
+#[turbo::serialize]
+pub enum LobbyAction {
+    CreateRoom,
+    JoinRoom(String),
+    RefreshRooms,
+    Leave,
+}
+
+#[turbo::serialize]
+pub struct LobbyMenu {
+    screen: LobbyScreen,
+    options: Vec<MenuOption>,
+    room_options: Vec<MenuOption>,
+    // Indices into ROOM_CODE_ALPHABET
+    code: Vec<usize>,
+    code_cursor: usize,
+    refresh_timer: u32,
+    status: Option<String>,
+}
+
+impl LobbyMenu {
+    pub fn new() -> Self {
+        let options = ["Create Room", "Join by Code", "Quick Match", "Back"]
+            .iter()
+            .enumerate()
+            .map(|(idx, option)| MenuOption::new(String::from(*option), MENU_X, FIRST_OPTION_Y + OPTION_HEIGHT * idx as i32, idx == 0))
+            .collect();
+        Self {
+            screen: LobbyScreen::Options,
+            options,
+            room_options: vec![],
+            code: vec![0; CODE_LENGTH],
+            code_cursor: 0,
+            refresh_timer: 0,
+            status: None,
+        }
+    }
+
+    pub fn is_connecting(&self) -> bool {
+        self.screen == LobbyScreen::Connecting
+    }
+
+    pub fn set_open_rooms(&mut self, codes: Vec<String>) {
+        let selected_code = self.room_options.iter().find(|option| option.is_selected).map(|option| option.text.clone());
+        self.room_options = codes
+            .into_iter()
+            .take(MAX_LISTED_ROOMS)
+            .enumerate()
+            .map(|(idx, code)| MenuOption::new(code, MENU_X, FIRST_OPTION_Y + OPTION_HEIGHT * idx as i32, false))
+            .collect();
+
+        // Keep the cursor on the same room while the list refreshes
+        let selected_index = self.room_options.iter().position(|option| Some(&option.text) == selected_code.as_ref()).unwrap_or(0);
+        if let Some(option) = self.room_options.get_mut(selected_index) {
+            option.is_selected = true;
+        }
+    }
+
+    // Sends the player back to where the failed request came from
+    pub fn show_error(&mut self, message: String) {
+        self.status = Some(message);
+        self.screen = LobbyScreen::Options;
+    }
+
+    pub fn handle_input(&mut self, input_map: &InputMap) -> Option<LobbyAction> {
+        match self.screen {
+            LobbyScreen::Options => {
+                if input_map.just_pressed(Action::Back) {
+                    return Some(LobbyAction::Leave);
+                }
+                let selected_option = handle_input(&mut self.options, input_map)?;
+                self.status = None;
+                if selected_option == "Create Room" {
+                    self.screen = LobbyScreen::Connecting;
+                    return Some(LobbyAction::CreateRoom);
+                } else if selected_option == "Join by Code" {
+                    self.screen = LobbyScreen::EnterCode;
+                } else if selected_option == "Quick Match" {
+                    self.room_options.clear();
+                    self.refresh_timer = 0;
+                    self.screen = LobbyScreen::QuickMatch;
+                } else if selected_option == "Back" {
+                    return Some(LobbyAction::Leave);
+                }
+                None
+            },
+            LobbyScreen::EnterCode => {
+                let alphabet_len = ROOM_CODE_ALPHABET.len();
+                if input_map.just_pressed(Action::Up) {
+                    self.code[self.code_cursor] = (self.code[self.code_cursor] + 1) % alphabet_len;
+                }
+                if input_map.just_pressed(Action::Down) {
+                    self.code[self.code_cursor] = (self.code[self.code_cursor] + alphabet_len - 1) % alphabet_len;
+                }
+                if input_map.just_pressed(Action::Left) {
+                    self.code_cursor = (self.code_cursor + CODE_LENGTH - 1) % CODE_LENGTH;
+                }
+                if input_map.just_pressed(Action::Right) {
+                    self.code_cursor = (self.code_cursor + 1) % CODE_LENGTH;
+                }
+                if input_map.just_pressed(Action::Confirm) {
+                    self.screen = LobbyScreen::Connecting;
+                    return Some(LobbyAction::JoinRoom(self.get_code()));
+                }
+                if input_map.just_pressed(Action::Back) {
+                    self.screen = LobbyScreen::Options;
+                }
+                None
+            },
+            LobbyScreen::QuickMatch => {
+                if input_map.just_pressed(Action::Back) {
+                    self.screen = LobbyScreen::Options;
+                    return None;
+                }
+                if let Some(code) = handle_input(&mut self.room_options, input_map) {
+                    self.screen = LobbyScreen::Connecting;
+                    return Some(LobbyAction::JoinRoom(code));
+                }
+                if self.refresh_timer == 0 {
+                    self.refresh_timer = ROOM_LIST_REFRESH_FRAMES;
+                    return Some(LobbyAction::RefreshRooms);
+                }
+                self.refresh_timer -= 1;
+                None
+            },
+            LobbyScreen::Connecting => {
+                // Late answers are ignored once the player backed out
+                if input_map.just_pressed(Action::Back) {
+                    self.screen = LobbyScreen::Options;
+                }
+                None
+            },
+        }
+    }
+
+    fn get_code(&self) -> String {
+        self.code.iter().map(|idx| ROOM_CODE_ALPHABET[*idx] as char).collect()
+    }
+
+    pub fn draw(&self) {
+        text!("CO-OP", x = SCREEN_WIDTH / 2 - 20, y = 30, color = 0xffffffff, font = "large");
+
+        match self.screen {
+            LobbyScreen::Options => {
+                for option in &self.options {
+                    option.draw();
+                }
+            },
+            LobbyScreen::EnterCode => {
+                text!("Enter room code", x = SCREEN_WIDTH / 2 - 60, y = 80, color = 0xffffffff, font = "large");
+                let code = self.get_code();
+                for (idx, letter) in code.chars().enumerate() {
+                    let letter = letter.to_string();
+                    let color = if idx == self.code_cursor { SELECTED_COLOR } else { 0xffffffff };
+                    let x = SCREEN_WIDTH / 2 - 28 + idx as i32 * 16;
+                    text!(&letter, x = x, y = 110, color = color, font = "large");
+                    if idx == self.code_cursor {
+                        text!("-", x = x, y = 120, color = color, font = "large");
+                    }
+                }
+            },
+            LobbyScreen::QuickMatch => {
+                if self.room_options.is_empty() {
+                    text!("No open rooms yet...", x = SCREEN_WIDTH / 2 - 80, y = 100, color = 0xffffffff, font = "large");
+                }
+                for option in &self.room_options {
+                    option.draw();
+                }
+            },
+            LobbyScreen::Connecting => {
+                text!("Connecting...", x = SCREEN_WIDTH / 2 - 52, y = 110, color = 0xffffffff, font = "large");
+            },
+        }
+
+        if let Some(status) = &self.status {
+            text!(status, x = SCREEN_WIDTH / 2 - status.len() as i32 * 4, y = SCREEN_HEIGHT - 44, color = 0xff6060ff, font = "large");
+        }
+        text!("Back to leave", x = 40, y = SCREEN_HEIGHT - 24, color = 0x777777ff, font = "large");
+    }
+}
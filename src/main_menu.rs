@@ -15,7 +15,7 @@ pub fn draw_main_menu(main_menu_options: &mut Vec<MenuOption>) {
     sprite!(
         "acornr",
         x = 166,
-        y = 180,
+        y = 239,
     );
 }
 
@@ -25,7 +25,13 @@ pub enum GameFlowState {
     Credits,
     InGameSingle,
     InGameCoOp,
+    InGameLocalCoOp,
     WaitingForPlayer2,
+    Lobby,
+    Controls,
+    Replays,
+    TimeAttackMenu,
+    TimeAttackResults,
 }
 
 impl fmt::Display for GameFlowState {
@@ -35,7 +41,13 @@ impl fmt::Display for GameFlowState {
             GameFlowState::Credits => "Credits",
             GameFlowState::InGameSingle => "In Game Single",
             GameFlowState::InGameCoOp => "In Game CoOp",
+            GameFlowState::InGameLocalCoOp => "In Game Local CoOp",
             GameFlowState::WaitingForPlayer2 => "Waiting for Player 2",
+            GameFlowState::Lobby => "Lobby",
+            GameFlowState::Controls => "Controls",
+            GameFlowState::Replays => "Replays",
+            GameFlowState::TimeAttackMenu => "Time Attack Menu",
+            GameFlowState::TimeAttackResults => "Time Attack Results",
         };
         write!(f, "{}", state_str)
     }
@@ -43,14 +55,14 @@ impl fmt::Display for GameFlowState {
 
 #[turbo::serialize]
 pub struct MenuOption {
-    text: String,
+    pub text: String,
     position_x: i32,
     position_y: i32,
     pub is_selected: bool,
 }
 
 impl MenuOption {
-    fn new(text: String, position_x: i32, position_y: i32, is_selected: bool) -> Self {
+    pub fn new(text: String, position_x: i32, position_y: i32, is_selected: bool) -> Self {
         Self {
             text,
             position_x,
@@ -59,7 +71,7 @@ impl MenuOption {
         }
     }
     
-    fn draw(&self) {
+    pub fn draw(&self) {
         if self.is_selected {
             sprite!("menu-option-selected", x = self.position_x, y = self.position_y);
         } else {
@@ -73,9 +85,9 @@ pub fn get_main_menu_options() -> Vec<MenuOption> {
     let mut main_menu_options = Vec::new();
     
     let x_coord = 63;
-    let y_coord = 90;
-    let option_height = 30;
-    let options = vec!["START", "Co-Op", "Credits"];
+    let y_coord = 80;
+    let option_height = 22;
+    let options = ["START", "Time Attack", "Co-Op", "Local Co-Op", "Replays", "Controls", "Credits"];
     for (idx, option) in options.iter().enumerate() {
         main_menu_options.push(MenuOption::new(String::from(*option), x_coord, y_coord + option_height * idx as i32, idx == 0));
     }
@@ -83,18 +95,16 @@ pub fn get_main_menu_options() -> Vec<MenuOption> {
     main_menu_options
 }
 
-pub fn handle_input(options: &mut Vec<MenuOption>) -> Option<String> {
-    let gp = gamepad::get(0);
-    
-    if gp.up.just_pressed() {
+pub fn handle_input(options: &mut Vec<MenuOption>, input_map: &InputMap) -> Option<String> {
+    if input_map.just_pressed(Action::Up) {
         cycle_option(options, CycleDirection::Up);
     }
 
-    if gp.down.just_pressed() {
+    if input_map.just_pressed(Action::Down) {
         cycle_option(options, CycleDirection::Down);
     }
 
-    if gp.b.just_pressed() {
+    if input_map.just_pressed(Action::Confirm) {
         let selected_option = options.iter().position(|x| x.is_selected);
         match selected_option {
             Some(idx) => {
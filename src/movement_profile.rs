@@ -0,0 +1,109 @@
+use crate::*;
+
+const MOVEMENT_PROFILES_DATA: &str = include_str!("../data/movement_profiles.json");
+pub const DEFAULT_MOVEMENT_PROFILE: &str = "default";
+const MOVEMENT_ZONE_COLOR: u32 = 0x4be5fe18;
+
+// Parsed on first use, levels and the debug menu look profiles up by name a lot
+static MOVEMENT_PROFILES: std::sync::OnceLock<Vec<MovementProfile>> = std::sync::OnceLock::new();
+
+#[turbo::serialize]
+pub struct MovementProfile {
+    pub name: String,
+    pub gravity: f32,
+    pub max_gravity: f32,
+    pub move_speed_max: f32,
+    pub acceleration: f32,
+    pub deceleration: f32,
+    pub jump_force: f32,
+    pub coyote_timer_duration: i32,
+    pub jump_buffer_timer_duration: i32,
+    pub dash_timer: u32,
+    pub dash_speed_x: f32,
+    pub dash_speed_y: f32,
+    pub dash_flux_threshold: f32,
+    pub wall_slide_speed: f32,
+    pub wall_jump_input_lock: u32,
+}
+
+impl MovementProfile {
+    // Used when the profile data is missing or can't be parsed
+    pub fn new() -> Self {
+        Self {
+            name: DEFAULT_MOVEMENT_PROFILE.to_string(),
+            gravity: 1.4,
+            max_gravity: 15.,
+            move_speed_max: 4.0,
+            acceleration: 0.5,
+            deceleration: 0.5,
+            jump_force: 13.0,
+            coyote_timer_duration: 3,
+            jump_buffer_timer_duration: 8,
+            dash_timer: 6,
+            dash_speed_x: 12.,
+            dash_speed_y: 8.,
+            dash_flux_threshold: 10.,
+            wall_slide_speed: 2.,
+            wall_jump_input_lock: 8,
+        }
+    }
+}
+
+// Area of a level where a different movement profile applies, e.g. a low-gravity zone
+#[turbo::serialize]
+pub struct MovementZone {
+    pub solid: Solid,
+    pub profile: MovementProfile,
+}
+
+impl MovementZone {
+    pub fn new(position_x: f32, position_y: f32, width: f32, height: f32, profile_name: &str) -> Self {
+        Self {
+            solid: Solid {
+                position: Vector2 { x: position_x, y: position_y },
+                width,
+                height,
+            },
+            profile: get_movement_profile(profile_name),
+        }
+    }
+
+    pub fn draw(&self) {
+        let BoundingBox { top, right, bottom, left } = self.solid.get_bound();
+        rect!(
+            w = (right - left) as u32,
+            h = (bottom - top) as u32,
+            x = left as i32,
+            y = top as i32,
+            color = MOVEMENT_ZONE_COLOR,
+        );
+    }
+}
+
+pub fn load_movement_profiles() -> &'static [MovementProfile] {
+    MOVEMENT_PROFILES.get_or_init(|| match serde_json::from_str::<Vec<MovementProfile>>(MOVEMENT_PROFILES_DATA) {
+        Ok(profiles) => profiles,
+        Err(err) => {
+            log!("Could not parse movement profiles: {}", err);
+            vec![MovementProfile::new()]
+        }
+    })
+}
+
+pub fn get_movement_profile(name: &str) -> MovementProfile {
+    match load_movement_profiles().iter().find(|profile| profile.name == name) {
+        Some(profile) => profile.clone(),
+        None => {
+            log!("Unknown movement profile: {}", name);
+            MovementProfile::new()
+        }
+    }
+}
+
+// Zones take priority over the profile of the level
+pub fn get_movement_profile_at<'a>(position: &Vector2, level_profile: &'a MovementProfile, zones: &'a [MovementZone]) -> &'a MovementProfile {
+    match zones.iter().find(|zone| zone.solid.get_bound().contains(*position)) {
+        Some(zone) => &zone.profile,
+        None => level_profile,
+    }
+}
@@ -9,20 +9,22 @@ const MAX_PARTICLE_TAIL_COUNT: u32 = 9;
 pub struct ParticleManager {
     particle_pool: Vec<Particle>,
     flux_cores: Vec<FluxCore>,
+    danger_threshold: f32,
 }
 
 impl ParticleManager {
-    pub fn new(flux_cores: Vec<FluxCore>) -> Self {
+    pub fn new(flux_cores: Vec<FluxCore>, danger_threshold: f32) -> Self {
         Self {
             particle_pool: vec![],
             flux_cores: flux_cores,
+            danger_threshold,
         }
     }
     
-    pub fn update(&mut self, flux_cores: &Vec<FluxCore>) {
+    pub fn update(&mut self, flux_cores: &Vec<FluxCore>, frame: u32) {
         let mut dead_particle_indices: Vec<usize> = Vec::new();
         for (idx, particle) in &mut self.particle_pool.iter_mut().enumerate() {
-            particle.update(flux_cores);
+            particle.update(flux_cores, frame);
             if !particle.is_alive {
                 dead_particle_indices.push(idx);
             }
@@ -35,10 +37,10 @@ impl ParticleManager {
         }
     }
     
-    pub fn draw(&self) {
+    pub fn draw(&self, frame: u32) {
         for particle in &self.particle_pool {
             if particle.is_alive {
-                particle.draw(&self.flux_cores);
+                particle.draw(&self.flux_cores, self.danger_threshold, frame);
             } else {
                 // This is fine because all the active particles will be on the left side
                 break;
@@ -88,7 +90,7 @@ impl Particle {
         }
     }
     
-    fn update(&mut self, flux_cores: &Vec<FluxCore>) {
+    fn update(&mut self, flux_cores: &Vec<FluxCore>, frame: u32) {
         if !self.is_alive {
             // TODO: Particle should disappear gracefuly
             return;
@@ -111,7 +113,7 @@ impl Particle {
             Some(particle) => {
                 let prev_position = particle.clone();
                 let new_position = self.random_walk(prev_position);
-                let net_flux_field = net_flux_field_at_point(particle, &flux_cores);
+                let net_flux_field = net_flux_field_at_point(particle, flux_cores, frame);
                 self.positions.push_back(new_position + (net_flux_field * FLUX_FIELD_CONSTANT).clamp_length(MAX_PARTICLE_SPEED));
             },
             None => {},
@@ -125,14 +127,14 @@ impl Particle {
         position + Vector2::random() * self.jitter
     }
     
-    fn draw(&self, flux_cores: &Vec<FluxCore>) {
+    fn draw(&self, flux_cores: &Vec<FluxCore>, danger_threshold: f32, frame: u32) {
         // NOTE: This can be optimized to only generate the new color when creating the point
         let mut i = 1;
         let mut alpha = 0.6;
         for position in &self.positions {
-            let flux_strength = net_flux_field_at_point(&position, flux_cores);
+            let flux_strength = net_flux_field_at_point(position, flux_cores, frame);
             let color;
-            if flux_strength.length() >= DEATH_THRESHOLD {
+            if flux_strength.length() >= danger_threshold {
                 color = random_dangerous_color(alpha);
             } else {
                 color = self.color;
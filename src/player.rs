@@ -1,9 +1,7 @@
 use crate::*;
 
-const DASH_TIMER: u32 = 6;
-const DASH_SPEED_X: f32 = 12.;
-const DASH_SPEED_Y: f32 = 8.;
-const DASH_FLUX_THRESHOLD: f32 = 10.;
+const PLAYER_WIDTH: f32 = 20.;
+const PLAYER_HEIGHT: f32 = 35.;
 
 #[turbo::serialize]
 pub struct Player {
@@ -13,14 +11,6 @@ pub struct Player {
     is_facing_left: bool,
     coyote_timer: i32,
     jump_buffer_timer: i32,
-    gravity: f32,
-    max_gravity: f32,
-    move_speed_max: f32,
-    acceleration: f32,
-    deceleration: f32,
-    jump_force: f32,
-    coyote_timer_duration: i32,
-    jump_buffer_timer_duration: i32,
     movement_status: MovementStatus,
     has_landed: bool,
     try_pick_item: bool,
@@ -30,6 +20,9 @@ pub struct Player {
     dash_timer: u32,
     dash_force: Vector2,
     dash_direction: DashDirection,
+    wall_contact: Option<WallSide>,
+    wall_jump_lock_timer: u32,
+    pub exposure: Exposure,
 }
 
 impl Player {
@@ -40,19 +33,11 @@ impl Player {
     pub fn new_with_id(id: String, x: f32, y: f32) -> Self {
         Self {
             id,
-            actor: Actor::new(Vector2::new(x, y),20., 35.),
+            actor: Actor::new(Vector2::new(x, y), PLAYER_WIDTH, PLAYER_HEIGHT),
             velocity: Vector2::new(0., 0.),
-            move_speed_max: 4.0,
             coyote_timer: 0,
             jump_buffer_timer: 0,
-            gravity: 1.4,
-            max_gravity: 15.,
             is_facing_left: true,
-            acceleration: 0.5,
-            deceleration: 0.5,
-            jump_force: 13.0,
-            coyote_timer_duration: 3,
-            jump_buffer_timer_duration: 8,
             movement_status: MovementStatus::IsFalling,
             has_landed: false,
             try_pick_item: false,
@@ -62,6 +47,9 @@ impl Player {
             dash_timer: 0,
             dash_force: Vector2::zero(),
             dash_direction: DashDirection::Right,
+            wall_contact: Option::None,
+            wall_jump_lock_timer: 0,
+            exposure: Exposure::new(),
         }
     }
    
@@ -70,13 +58,14 @@ impl Player {
         actor_manager: &mut ActorManager,
         particle_manager: &mut juice_particles::ParticleManager,
         user_input: &UserInput,
-        flux_field: Vector2
+        flux_field: Vector2,
+        movement_profile: &MovementProfile,
     ) {
         match self.movement_status {
             MovementStatus::IsLanded => {
                 if user_input.jump_just_pressed || self.jump_buffer_timer > 0 {
                     // Add jump force
-                    self.velocity.y = -self.jump_force;
+                    self.velocity.y = -movement_profile.jump_force;
                     self.movement_status = MovementStatus::InJump;
                     self.generate_jump_particles(particle_manager);
 
@@ -97,14 +86,12 @@ impl Player {
                 }
             },
             MovementStatus::InJump => {
-                if user_input.y_just_pressed  && !self.used_dash { 
-                    if flux_field.length() > DASH_FLUX_THRESHOLD {
-                        self.dash_timer = DASH_TIMER;
-                        self.movement_status = MovementStatus::InDash;
-                    }
+                if user_input.dash_just_pressed && !self.used_dash && flux_field.length() > movement_profile.dash_flux_threshold {
+                    self.dash_timer = movement_profile.dash_timer;
+                    self.movement_status = MovementStatus::InDash;
                 }
                 if user_input.jump_just_pressed && !self.used_double_jump {
-                    self.velocity.y = -self.jump_force;
+                    self.velocity.y = -movement_profile.jump_force;
                     self.used_double_jump = true;
                 }
                 if self.velocity.y > 0. {
@@ -115,41 +102,63 @@ impl Player {
                 if user_input.jump_just_pressed {
                     if self.coyote_timer > 0 {
                         // If coyote timer is active and jump is pressed, do a regular jump
-                        self.velocity.y = -self.jump_force;
+                        self.velocity.y = -movement_profile.jump_force;
                         self.movement_status = MovementStatus::InJump;
                         audio::play("jump-sfx-nothing");
                     } else {
                         if self.used_double_jump {
                             // If double jump already used, buffer jump
-                            self.jump_buffer_timer = self.jump_buffer_timer_duration;
+                            self.jump_buffer_timer = movement_profile.jump_buffer_timer_duration;
                         } else {
                             // Otherwise, double jump!
                             
-                            self.velocity.y = -self.jump_force;
+                            self.velocity.y = -movement_profile.jump_force;
                             self.movement_status = MovementStatus::InJump;
                             self.used_double_jump = true;
                         }
                     }
                 }
-                if user_input.y_just_pressed {
-                    if flux_field.length() > DASH_FLUX_THRESHOLD {
-                        self.dash_timer = DASH_TIMER;
-                        self.movement_status = MovementStatus::InDash;
-                    }
+                if user_input.dash_just_pressed && !self.used_dash && flux_field.length() > movement_profile.dash_flux_threshold {
+                    self.dash_timer = movement_profile.dash_timer;
+                    self.movement_status = MovementStatus::InDash;
+                } else if self.velocity.y > 0. && self.is_pushing_into_wall(user_input) {
+                    // Grabbing a wall gives the dash back, like landing does
+                    self.movement_status = MovementStatus::IsWallSliding;
+                    self.used_dash = false;
                 }
             }
+            MovementStatus::IsWallSliding => {
+                if user_input.jump_just_pressed {
+                    // Push away from the wall and briefly ignore horizontal input so the player can't cling back
+                    let away_from_wall = match self.wall_contact {
+                        Some(WallSide::Left) => 1.,
+                        _ => -1.,
+                    };
+                    self.velocity = Vector2::new(away_from_wall * movement_profile.move_speed_max, -movement_profile.jump_force);
+                    self.is_facing_left = away_from_wall < 0.;
+                    self.wall_jump_lock_timer = movement_profile.wall_jump_input_lock;
+                    self.movement_status = MovementStatus::InWallJump;
+                    self.generate_wall_jump_particles(particle_manager);
+
+                    let anim = animation::get("player_character_idle");
+                    anim.use_sprite("ChipmunckCharacter_jump");
+                    anim.set_speed(2.0);
+                    anim.set_repeat(1);
+
+                    audio::play("jump-sfx-nothing");
+                } else if !self.is_pushing_into_wall(user_input) {
+                    self.movement_status = MovementStatus::IsFalling;
+                }
+            },
+            MovementStatus::InWallJump => {
+                if self.wall_jump_lock_timer == 0 {
+                    self.movement_status = if self.velocity.y > 0. { MovementStatus::IsFalling } else { MovementStatus::InJump };
+                }
+            },
             MovementStatus::InDash => {
-                if self.dash_timer == DASH_TIMER {
-                    if user_input.right_pressed {
-                        self.dash_force = Vector2::new(DASH_SPEED_X, 0.);
-                        self.dash_direction = DashDirection::Right;
-                    } else if user_input.left_pressed {
-                        self.dash_force = Vector2::new(-DASH_SPEED_X, 0.);
-                        self.dash_direction = DashDirection::Left;
-                    } else {
-                        self.dash_timer = 0;
-                        return;
-                    }
+                if self.dash_timer == movement_profile.dash_timer {
+                    self.dash_direction = DashDirection::from_input(user_input, self.is_facing_left);
+                    self.dash_force = self.dash_direction.get_dash_force(movement_profile);
                 }
                 if self.dash_timer > 0 {
                     self.velocity = self.dash_force;
@@ -164,33 +173,41 @@ impl Player {
             }
         }
 
-        if user_input.left_pressed {
-            self.velocity += &Vector2::new(-self.acceleration, 0.0);
+        if self.wall_jump_lock_timer > 0 {
+            // Keep the momentum of the wall jump
+            self.wall_jump_lock_timer -= 1;
+        } else if user_input.left_pressed {
+            self.velocity += &Vector2::new(-movement_profile.acceleration, 0.0);
             self.is_facing_left = true;
             self.generate_run_particles(particle_manager);
         } else if user_input.right_pressed {
-            self.velocity += & Vector2::new(self.acceleration, 0.0);
+            self.velocity += & Vector2::new(movement_profile.acceleration, 0.0);
             self.is_facing_left = false;
             self.generate_run_particles(particle_manager);
         } else {
             if self.velocity.x > 0. {
-                self.velocity.x = (self.velocity.x - self.deceleration).max(0.);
+                self.velocity.x = (self.velocity.x - movement_profile.deceleration).max(0.);
             } else if self.velocity.x < 0. {
-                self.velocity.x = (self.velocity.x + self.deceleration).min(0.);
+                self.velocity.x = (self.velocity.x + movement_profile.deceleration).min(0.);
             }
         }
 
-        self.velocity.clamp_x(-self.move_speed_max, self.move_speed_max);
+        self.velocity.clamp_x(-movement_profile.move_speed_max, movement_profile.move_speed_max);
         let current_gravity = if self.movement_status == MovementStatus::IsFalling && !user_input.jump_pressed
             {
-                Vector2::new(0., self.gravity * 0.7)
+                Vector2::new(0., movement_profile.gravity * 0.7)
             } else if self.velocity.y.abs() < 4.0 {
-                Vector2::new(0., self.gravity / 4.)
+                Vector2::new(0., movement_profile.gravity / 4.)
             } else { 
-                Vector2::new(0.,  self.gravity )
+                Vector2::new(0.,  movement_profile.gravity )
             };
         self.velocity += &current_gravity;
-        self.velocity.clamp_y(-self.jump_force, self.max_gravity);
+        self.velocity.clamp_y(-movement_profile.jump_force, movement_profile.max_gravity);
+
+        if self.movement_status == MovementStatus::IsWallSliding {
+            self.velocity.y = self.velocity.y.min(movement_profile.wall_slide_speed);
+            self.generate_wall_slide_particles(particle_manager);
+        }
 
         if self.coyote_timer > 0 {
             self.coyote_timer -= 1;
@@ -200,7 +217,7 @@ impl Player {
             self.jump_buffer_timer -= 1;
         }
 
-        if user_input.a_just_pressed {
+        if user_input.pick_just_pressed {
             match self.picked_item {
                 None => {
                     self.try_pick_item = true;
@@ -220,6 +237,10 @@ impl Player {
         if self.has_landed {
             self.generate_land_particles(particle_manager);
         }
+
+        if self.exposure.is_warning() {
+            self.generate_exposure_particles(particle_manager);
+        }
     }
     
     fn generate_run_particles(&self, particle_manager: &mut juice_particles::ParticleManager) {
@@ -278,23 +299,101 @@ impl Player {
         });
     }
 
-    fn generate_dash_particles(&self, particle_manager: &mut juice_particles::ParticleManager, dash_direction: &DashDirection) {
+    fn generate_exposure_particles(&self, particle_manager: &mut juice_particles::ParticleManager) {
         let bounding_box = self.actor.get_bound();
-        let dash_particle_fast_speed = 1.0;
-        let dash_particle_slow_speed = 0.1;
-        let x_velocity;
-        let y_velocity;
-        
-        match dash_direction {
-            DashDirection::Right => {
-                x_velocity = (dash_particle_slow_speed, dash_particle_fast_speed);
-                y_velocity = (-dash_particle_slow_speed, dash_particle_slow_speed);
+        // Emit more particles the closer the player gets to lethal exposure
+        let count = (self.exposure.get_value() * 4.) as u32;
+        particle_manager.create_burst( &BurstConfig {
+            source: BurstSource::Rectangle {
+                min: (bounding_box.left, bounding_box.top),
+                max: (bounding_box.right, bounding_box.bottom),
             },
-            DashDirection::Left => {
-                x_velocity = (-dash_particle_fast_speed, -dash_particle_slow_speed);
-                y_velocity = (-dash_particle_slow_speed, dash_particle_slow_speed);
+            x_velocity: (-0.4, 0.4),
+            y_velocity: (-0.8, -0.2),
+            lifetime: (0.2, 0.6),
+            color: random_dangerous_color(0.8),
+            size: (1, 4),
+            count,
+            shape: Shape::Square,
+            should_fade_out: true,
+        });
+    }
+
+    fn generate_wall_slide_particles(&self, particle_manager: &mut juice_particles::ParticleManager) {
+        let bounding_box = self.actor.get_bound();
+        let wall_x = match self.wall_contact {
+            Some(WallSide::Left) => bounding_box.left,
+            _ => bounding_box.right,
+        };
+        particle_manager.create_burst(&BurstConfig {
+            source: BurstSource::Rectangle {
+                min: (wall_x - 1., bounding_box.top),
+                max: (wall_x + 1., bounding_box.top + 4.),
+            },
+            x_velocity: (-0.1, 0.1),
+            y_velocity: (-0.3, -0.1),
+            lifetime: (0.2, 0.6),
+            color: 0x777777cc,
+            size: (1, 3),
+            count: 1,
+            shape: Shape::Square,
+            should_fade_out: true,
+        });
+    }
+
+    fn generate_wall_jump_particles(&self, particle_manager: &mut juice_particles::ParticleManager) {
+        let bounding_box = self.actor.get_bound();
+        let (wall_x, x_velocity) = match self.wall_contact {
+            Some(WallSide::Left) => (bounding_box.left, (0.1, 0.8)),
+            _ => (bounding_box.right, (-0.8, -0.1)),
+        };
+        particle_manager.create_burst(&BurstConfig {
+            source: BurstSource::Rectangle {
+                min: (wall_x - 2., bounding_box.top),
+                max: (wall_x, bounding_box.bottom),
             },
+            x_velocity,
+            y_velocity: (-0.5, 0.5),
+            lifetime: (0.2, 0.8),
+            color: 0x777777cc,
+            size: (1, 5),
+            count: 10,
+            shape: Shape::Circle,
+            should_fade_out: true,
+        });
+    }
+
+    fn is_pushing_into_wall(&self, user_input: &UserInput) -> bool {
+        match self.wall_contact {
+            Some(WallSide::Left) => user_input.left_pressed,
+            Some(WallSide::Right) => user_input.right_pressed,
+            None => false,
         }
+    }
+
+    pub fn generate_death_particles(&self, particle_manager: &mut juice_particles::ParticleManager) {
+        let position = self.actor.position;
+        particle_manager.create_burst( &BurstConfig {
+            source: BurstSource::Circle {
+                center: (position.x, position.y),
+                radius: 10.,
+            },
+            x_velocity: (-2.0, 2.0),
+            y_velocity: (-2.5, 1.0),
+            lifetime: (0.4, 1.2),
+            color: 0xff3b3bcc,
+            size: (2, 6),
+            count: 40,
+            shape: Shape::Circle,
+            should_fade_out: true,
+        });
+    }
+
+    fn generate_dash_particles(&self, particle_manager: &mut juice_particles::ParticleManager, dash_direction: &DashDirection) {
+        let bounding_box = self.actor.get_bound();
+        let direction = dash_direction.get_unit_vector();
+        let x_velocity = get_dash_particle_velocity_range(direction.x);
+        let y_velocity = get_dash_particle_velocity_range(direction.y);
 
         particle_manager.create_burst( &BurstConfig {
             source: BurstSource::Rectangle {
@@ -344,21 +443,49 @@ impl Player {
         }
     }
     
-    pub fn actor_move(&mut self, tiles: &Vec<&Solid>, actor_manager: &mut ActorManager) {
+    pub fn drop_item(&mut self) {
+        self.picked_item = Option::None;
+    }
+
+    // Moves the player without collision checks, so only onto a spot known to be free: back
+    // along the path it just took or onto a partner. Stops the motion along the moved axes.
+    pub fn shift(&mut self, offset: &Vector2) {
+        self.actor.position += offset;
+        if offset.x != 0. {
+            self.velocity.x = 0.;
+        }
+        if offset.y != 0. {
+            self.velocity.y = 0.;
+        }
+    }
+
+    pub fn actor_move(&mut self, tiles: &Vec<&Solid>, actor_manager: &mut ActorManager, movement_profile: &MovementProfile) {
         let current_velocity_x = self.velocity.x;
         let current_velocity_y = self.velocity.y;
 
         let on_x_collision = || {
+            self.wall_contact = if current_velocity_x > 0. { Some(WallSide::Right) } else { Some(WallSide::Left) };
             self.velocity.x = 0.;
         };
         self.actor.move_x(tiles, current_velocity_x, on_x_collision);
 
+        // Wall contact lasts only while the player is still touching that wall
+        if let Some(wall_side) = &self.wall_contact {
+            let wall_offset = match wall_side {
+                WallSide::Left => -1.,
+                WallSide::Right => 1.,
+            };
+            if !collide_at(tiles, &(self.actor.get_bound() + &Vector2::new(wall_offset, 0.))) {
+                self.wall_contact = Option::None;
+            }
+        }
+
         let on_y_collision = |collision_happened: bool| {
             if collision_happened {
                 if self.velocity.y >= 0.0 {
                     self.used_dash = false;
                     self.used_double_jump = false;
-                    if self.movement_status == MovementStatus::IsFalling {
+                    if self.movement_status == MovementStatus::IsFalling || self.movement_status == MovementStatus::IsWallSliding {
                         if self.velocity.x.abs() > 0.1 {
                             let anim = animation::get("player_character_walk");
                             anim.use_sprite("ChipmunckCharacter_land");
@@ -382,7 +509,7 @@ impl Player {
                 self.velocity.y = 0.;
             } else {
                 if self.movement_status == MovementStatus::IsLanded {
-                    self.coyote_timer = self.coyote_timer_duration;
+                    self.coyote_timer = movement_profile.coyote_timer_duration;
                     self.movement_status = MovementStatus::IsFalling;
                 }
                 self.has_landed = false;
@@ -416,47 +543,106 @@ impl Player {
     pub fn get_position(&self) -> Vector2 {
         Vector2 { x: self.actor.position.x, y: self.actor.position.y }
     }
-    
-    pub fn draw(&self) {
-        let x_offset_holder = if self.is_facing_left { 17. } else { 19. };
-        let y_offset_holder = 18.;
-        
-        match self.picked_item {
-            Some(_) => {
-                sprite!(
-                    "energy_box_holder",
-                    x = self.actor.position.x - x_offset_holder,
-                    y = self.actor.position.y - y_offset_holder,
-                    flip_x = self.is_facing_left,
-                )
+
+    // Blends two server snapshots of a remote player. Discrete state like facing and movement
+    // status can't be blended, it comes from the snapshot the render time has already reached.
+    pub fn interpolate(&self, other: &Player, alpha: f32) -> Player {
+        let mut player = if alpha < 1. { self.clone() } else { other.clone() };
+        player.actor.position = self.get_position().lerp(&other.get_position(), alpha);
+        player.velocity = self.velocity.lerp(&other.velocity, alpha);
+        player
+    }
+
+    // What changed since the player a client already has, None when nothing did. A player the
+    // baseline doesn't know yet is sent whole.
+    pub fn diff(&self, baseline: Option<&Player>) -> Option<PlayerDelta> {
+        let mut delta = PlayerDelta {
+            id: self.id.clone(),
+            position: None,
+            remainder: None,
+            velocity: None,
+            movement_status: None,
+            player: None,
+        };
+        let baseline = match baseline {
+            Some(baseline) => baseline,
+            None => {
+                delta.player = Some(self.clone());
+                return Some(delta);
             },
-            None => {}
+        };
+
+        // Everything besides the fields sent on their own is compared in one go
+        let mut rest = self.clone();
+        rest.actor.position = baseline.actor.position;
+        rest.actor.remainder = baseline.actor.remainder;
+        rest.velocity = baseline.velocity;
+        rest.movement_status = baseline.movement_status;
+        if !is_same_bytes(&rest, baseline) {
+            delta.player = Some(self.clone());
+            return Some(delta);
         }
 
-        let BoundingBox {top, right, bottom, left} = self.actor.get_bound();
-        if self.movement_status == MovementStatus::IsLanded && self.velocity.x != 0. {
-            let x_offset = if self.is_facing_left { 5 } else { 10 };
-            let y_offset = 0;
-            sprite!(
-                animation_key = "player_character_walk",
-                default_sprite = "ChipmunckCharacter_walk",
-                x = left as i32 - x_offset,
-                y = top as i32 - y_offset,
-                flip_x = self.is_facing_left,
-            );
-        } else {
-            let x_offset = if self.is_facing_left { 5 } else { 10 };
-            let y_offset = 0;
-            sprite!(
-                animation_key = "player_character_idle",
-                default_sprite = "ChipmunckCharacter_idle_36",
-                x = left as i32 - x_offset,
-                y = top as i32 - y_offset,
-                flip_x = self.is_facing_left,
-            );
+        let position = QuantizedPosition::from_vector(&self.actor.position);
+        if position != QuantizedPosition::from_vector(&baseline.actor.position) {
+            delta.position = Some(position);
+        }
+        if !is_same_bytes(&self.actor.remainder, &baseline.actor.remainder) {
+            delta.remainder = Some(self.actor.remainder);
         }
+        if !is_same_bytes(&self.velocity, &baseline.velocity) {
+            delta.velocity = Some(self.velocity);
+        }
+        if self.movement_status != baseline.movement_status {
+            delta.movement_status = Some(self.movement_status);
+        }
+
+        let is_unchanged = delta.position.is_none() && delta.remainder.is_none() && delta.velocity.is_none() && delta.movement_status.is_none();
+        if is_unchanged { None } else { Some(delta) }
+    }
+
+    pub fn get_picked_item(&self) -> Option<ActorId> {
+        self.picked_item
+    }
+
+    pub fn get_look_direction(&self) -> f32 {
+        if self.is_facing_left { -1. } else { 1. }
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.movement_status == MovementStatus::IsLanded
+    }
+
+    pub fn is_dashing(&self) -> bool {
+        self.movement_status == MovementStatus::InDash
     }
     
+    pub fn draw(&self) {
+        self.draw_with_color(0xffffffff);
+    }
+
+    pub fn draw_with_color(&self, color: u32) {
+        self.get_pose().draw(color);
+    }
+
+    // Everything drawing needs, small enough to keep one per frame for a ghost
+    pub fn get_pose(&self) -> PlayerPose {
+        let animation = if self.movement_status == MovementStatus::IsWallSliding {
+            // Face away from the wall while sliding down
+            PlayerAnimation::WallSlide { is_facing_left: matches!(self.wall_contact, Some(WallSide::Right)) }
+        } else if self.movement_status == MovementStatus::IsLanded && self.velocity.x != 0. {
+            PlayerAnimation::Walk
+        } else {
+            PlayerAnimation::Idle
+        };
+        PlayerPose {
+            position: QuantizedPosition::from_vector(&self.actor.position),
+            animation,
+            is_facing_left: self.is_facing_left,
+            is_carrying: self.picked_item.is_some(),
+        }
+    }
+
     pub fn draw_bounding_box(&self) {
         self.actor.get_bound().draw_bounding_box();
     }
@@ -500,18 +686,207 @@ pub fn draw_menu_distortion_parameter_pixel() {
 }
 
 #[turbo::serialize]
-#[derive(PartialEq)]
+#[derive(Copy)]
+pub enum PlayerAnimation {
+    Idle,
+    Walk,
+    WallSlide {
+        is_facing_left: bool,
+    },
+}
+
+#[turbo::serialize]
+#[derive(Copy)]
+pub struct PlayerPose {
+    position: QuantizedPosition,
+    animation: PlayerAnimation,
+    is_facing_left: bool,
+    is_carrying: bool,
+}
+
+impl PlayerPose {
+    pub fn new(position: QuantizedPosition, animation: PlayerAnimation, is_facing_left: bool, is_carrying: bool) -> Self {
+        Self {
+            position,
+            animation,
+            is_facing_left,
+            is_carrying,
+        }
+    }
+
+    pub fn draw(&self, color: u32) {
+        let position = self.position.to_vector();
+        let x_offset_holder = if self.is_facing_left { 17. } else { 19. };
+        let y_offset_holder = 18.;
+        
+        if self.is_carrying {
+            sprite!(
+                "energy_box_holder",
+                x = position.x - x_offset_holder,
+                y = position.y - y_offset_holder,
+                flip_x = self.is_facing_left,
+            )
+        }
+
+        let BoundingBox { top, left, .. } = BoundingBox::from_center(&position, PLAYER_WIDTH, PLAYER_HEIGHT);
+        match self.animation {
+            PlayerAnimation::WallSlide { is_facing_left } => {
+                let x_offset = if is_facing_left { 5 } else { 10 };
+                sprite!(
+                    animation_key = "player_character_wall_slide",
+                    default_sprite = "ChipmunckCharacter_land",
+                    x = left as i32 - x_offset,
+                    y = top as i32,
+                    flip_x = is_facing_left,
+                    color = color,
+                );
+            },
+            PlayerAnimation::Walk => {
+                let x_offset = if self.is_facing_left { 5 } else { 10 };
+                sprite!(
+                    animation_key = "player_character_walk",
+                    default_sprite = "ChipmunckCharacter_walk",
+                    x = left as i32 - x_offset,
+                    y = top as i32,
+                    flip_x = self.is_facing_left,
+                    color = color,
+                );
+            },
+            PlayerAnimation::Idle => {
+                let x_offset = if self.is_facing_left { 5 } else { 10 };
+                sprite!(
+                    animation_key = "player_character_idle",
+                    default_sprite = "ChipmunckCharacter_idle_36",
+                    x = left as i32 - x_offset,
+                    y = top as i32,
+                    flip_x = self.is_facing_left,
+                    color = color,
+                );
+            },
+        }
+    }
+}
+
+// Only the player fields that changed since a baseline snapshot. Position, velocity and status
+// change nearly every frame and are sent on their own, anything else sends the whole player.
+#[turbo::serialize]
+pub struct PlayerDelta {
+    pub id: String,
+    position: Option<QuantizedPosition>,
+    remainder: Option<Vector2>,
+    velocity: Option<Vector2>,
+    movement_status: Option<MovementStatus>,
+    player: Option<Player>,
+}
+
+impl PlayerDelta {
+    pub fn apply(self, players: &mut BTreeMap<String, Player>) {
+        if let Some(player) = self.player {
+            players.insert(self.id, player);
+            return;
+        }
+        let player = match players.get_mut(&self.id) {
+            Some(player) => player,
+            None => return,
+        };
+        if let Some(position) = self.position {
+            player.actor.position = position.to_vector();
+        }
+        if let Some(remainder) = self.remainder {
+            player.actor.remainder = remainder;
+        }
+        if let Some(velocity) = self.velocity {
+            player.velocity = velocity;
+        }
+        if let Some(movement_status) = self.movement_status {
+            player.movement_status = movement_status;
+        }
+    }
+}
+
+#[turbo::serialize]
+#[derive(Copy, PartialEq)]
 enum MovementStatus {
     IsLanded,
     IsFalling,
     InJump,
     InDash,
+    IsWallSliding,
+    InWallJump,
+}
+
+#[turbo::serialize]
+enum WallSide {
+    Left,
+    Right,
 }
 
 #[turbo::serialize]
 enum DashDirection {
     Right,
     Left,
+    Up,
+    Down,
+    UpRight,
+    UpLeft,
+    DownRight,
+    DownLeft,
+}
+
+impl DashDirection {
+    // Dashes towards the held direction, or forward if no direction is held
+    fn from_input(user_input: &UserInput, is_facing_left: bool) -> Self {
+        let horizontal = (user_input.right_pressed as i32) - (user_input.left_pressed as i32);
+        let vertical = (user_input.down_pressed as i32) - (user_input.up_pressed as i32);
+        match (horizontal, vertical) {
+            (1, 0) => DashDirection::Right,
+            (-1, 0) => DashDirection::Left,
+            (0, -1) => DashDirection::Up,
+            (0, 1) => DashDirection::Down,
+            (1, -1) => DashDirection::UpRight,
+            (-1, -1) => DashDirection::UpLeft,
+            (1, 1) => DashDirection::DownRight,
+            (-1, 1) => DashDirection::DownLeft,
+            _ => if is_facing_left { DashDirection::Left } else { DashDirection::Right },
+        }
+    }
+
+    fn get_unit_vector(&self) -> Vector2 {
+        match self {
+            DashDirection::Right => Vector2::new(1., 0.),
+            DashDirection::Left => Vector2::new(-1., 0.),
+            DashDirection::Up => Vector2::new(0., -1.),
+            DashDirection::Down => Vector2::new(0., 1.),
+            DashDirection::UpRight => Vector2::new(1., -1.),
+            DashDirection::UpLeft => Vector2::new(-1., -1.),
+            DashDirection::DownRight => Vector2::new(1., 1.),
+            DashDirection::DownLeft => Vector2::new(-1., 1.),
+        }
+    }
+
+    fn get_dash_force(&self, movement_profile: &MovementProfile) -> Vector2 {
+        let direction = self.get_unit_vector();
+        let dash_force = Vector2::new(direction.x * movement_profile.dash_speed_x, direction.y * movement_profile.dash_speed_y);
+        // Keep diagonal dashes from covering more distance than straight ones
+        if direction.x != 0. && direction.y != 0. {
+            dash_force * std::f32::consts::FRAC_1_SQRT_2
+        } else {
+            dash_force
+        }
+    }
+}
+
+// Particles trail along the dash, spreading slightly on the axes the dash doesn't move in
+fn get_dash_particle_velocity_range(direction: f32) -> (f32, f32) {
+    let dash_particle_fast_speed = 1.0;
+    let dash_particle_slow_speed = 0.1;
+    if direction > 0. {
+        (dash_particle_slow_speed, dash_particle_fast_speed)
+    } else if direction < 0. {
+        (-dash_particle_fast_speed, -dash_particle_slow_speed)
+    } else {
+        (-dash_particle_slow_speed, dash_particle_slow_speed)
+    }
 }
 
 impl std::fmt::Display for MovementStatus {
@@ -521,6 +896,77 @@ impl std::fmt::Display for MovementStatus {
             MovementStatus::IsFalling => write!(f, "IsFalling"),
             MovementStatus::IsLanded => write!(f, "IsLanded"),
             MovementStatus::InDash => write!(f, "InDash"),
+            MovementStatus::IsWallSliding => write!(f, "IsWallSliding"),
+            MovementStatus::InWallJump => write!(f, "InWallJump"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(player: &mut Player, solids: &Vec<&Solid>, input: &UserInput) {
+        let movement_profile = get_movement_profile(DEFAULT_MOVEMENT_PROFILE);
+        let mut actor_manager = ActorManager::new();
+        let mut particle_manager = juice_particles::ParticleManager::new();
+        player.handle_input(&mut actor_manager, &mut particle_manager, input, Vector2::zero(), &movement_profile);
+        player.actor_move(solids, &mut actor_manager, &movement_profile);
+    }
+
+    fn solid(x: f32, y: f32, width: f32, height: f32) -> Solid {
+        Solid { position: Vector2::new(x, y), width, height }
+    }
+
+    #[test]
+    fn interpolation_keeps_discrete_state_until_the_next_snapshot_is_reached() {
+        let from = Player::new(0., 0.);
+        let mut to = Player::new(10., 0.);
+        to.is_facing_left = false;
+        to.movement_status = MovementStatus::InDash;
+
+        let player = from.interpolate(&to, 0.9);
+        assert!((player.get_position().x - 9.).abs() < 0.001);
+        assert!(player.is_facing_left);
+        assert!(!player.is_dashing());
+
+        let player = from.interpolate(&to, 1.);
+        assert!(!player.is_facing_left);
+        assert!(player.is_dashing());
+    }
+
+    #[test]
+    fn landing_refills_dash() {
+        let floor = solid(0., 100., 200., 16.);
+        let mut player = Player::new(0., 40.);
+        player.used_dash = true;
+        for tick in 0..60 {
+            step(&mut player, &vec![&floor], &UserInput { tick, ..UserInput::new() });
+        }
+        assert!(player.is_grounded());
+        assert!(!player.used_dash);
+    }
+
+    #[test]
+    fn wall_slide_refills_dash() {
+        // Falling along a wall to the right while holding right, far above any floor
+        let wall = solid(20. + PLAYER_WIDTH / 2. + 8., 0., 16., 1000.);
+        let mut player = Player::new(20., 0.);
+        player.used_dash = true;
+        for tick in 0..30 {
+            step(&mut player, &vec![&wall], &UserInput { tick, right_pressed: true, ..UserInput::new() });
+        }
+        assert_eq!(player.movement_status, MovementStatus::IsWallSliding);
+        assert!(!player.used_dash);
+    }
+
+    #[test]
+    fn airborne_dash_stays_used() {
+        let mut player = Player::new(0., 0.);
+        player.used_dash = true;
+        for tick in 0..30 {
+            step(&mut player, &vec![], &UserInput { tick, ..UserInput::new() });
         }
+        assert!(player.used_dash);
     }
-}
\ No newline at end of file
+}
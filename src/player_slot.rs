@@ -0,0 +1,42 @@
+use crate::*;
+
+pub const MAX_PLAYERS: usize = 4;
+
+// Everything the co-op server tracks for one connected player
+#[turbo::serialize]
+pub struct PlayerSlot {
+    // Picks the spawn point, stays the same for the whole session
+    pub index: usize,
+    pub player: Player,
+    pub input_buffer: InputJitterBuffer,
+    pub is_ready: bool,
+    // Newest snapshot the client decoded, used as the delta baseline
+    pub acknowledged_snapshot: Option<u32>,
+    // Tick of the last input simulated during the current interval
+    pub last_processed_tick: Option<usize>,
+    // Server ticks left until the slot is released, while the player is disconnected
+    pub disconnect_timer: Option<u32>,
+}
+
+impl PlayerSlot {
+    pub fn new(index: usize, user_id: &str, start_position: Vector2) -> Self {
+        Self {
+            index,
+            player: Player::new_with_id(user_id.to_string(), start_position.x, start_position.y),
+            input_buffer: InputJitterBuffer::new(),
+            is_ready: false,
+            acknowledged_snapshot: None,
+            last_processed_tick: None,
+            disconnect_timer: None,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.disconnect_timer.is_none()
+    }
+}
+
+// Lowest slot index nobody holds yet, None when the room is full
+pub fn get_free_slot_index(slots: &BTreeMap<String, PlayerSlot>) -> Option<usize> {
+    (0..MAX_PLAYERS).find(|index| !slots.values().any(|slot| slot.index == *index))
+}
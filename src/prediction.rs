@@ -0,0 +1,158 @@
+use crate::*;
+
+const POSITION_TOLERANCE: f32 = 0.5;
+
+// Local state right after a predicted input was simulated
+#[turbo::serialize]
+pub struct PredictedFrame {
+    pub input: UserInput,
+    pub level_frame: u32,
+    pub player: Player,
+    pub harvesters: Vec<Harvester>,
+    pub actor_manager: ActorManager,
+}
+
+impl PredictedFrame {
+    fn diverges_from(&self, server_state: &ServerState) -> bool {
+        let ServerState { level_frame: server_level_frame, player: server_player, harvesters: server_harvesters, actor_manager: server_actor_manager, checksum: _ } = server_state;
+        // A different clock means different flux, even if nothing has drifted yet
+        if self.level_frame != *server_level_frame {
+            return true;
+        }
+
+        if !is_same_position(&self.player.get_position(), &server_player.get_position())
+            || self.player.get_picked_item() != server_player.get_picked_item() {
+            return true;
+        }
+
+        if self.harvesters.len() != server_harvesters.len()
+            || self.harvesters.iter().zip(server_harvesters).any(|(predicted, server)| predicted.actor != server.actor) {
+            return true;
+        }
+
+        // Harvesters are moved through their actors, so comparing the actors covers carried and pushed items
+        if self.actor_manager.actors.len() != server_actor_manager.actors.len() {
+            return true;
+        }
+        self.actor_manager.actors.iter().any(|(id, predicted)| match server_actor_manager.get_actor(*id) {
+            Some(server) => predicted.is_child != server.is_child || !is_same_position(&predicted.position, &server.position),
+            None => true,
+        })
+    }
+}
+
+fn is_same_position(a: &Vector2, b: &Vector2) -> bool {
+    (a.x - b.x).abs() <= POSITION_TOLERANCE && (a.y - b.y).abs() <= POSITION_TOLERANCE
+}
+
+// Ring buffer of predicted frames that haven't been acknowledged by the server yet
+#[turbo::serialize]
+pub struct PredictionBuffer {
+    frames: VecDeque<PredictedFrame>,
+    capacity: usize,
+}
+
+impl PredictionBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, input: &UserInput, player: &Player, level: &Level) {
+        self.frames.push_back(PredictedFrame {
+            input: input.clone(),
+            level_frame: level.frame,
+            player: player.clone(),
+            harvesters: level.harvesters.clone(),
+            actor_manager: level.actor_manager.clone(),
+        });
+        while self.frames.len() > self.capacity {
+            self.frames.pop_front();
+        }
+    }
+
+    // Inputs of the newest frames, oldest first. They are all still waiting for an ack.
+    pub fn get_recent_inputs(&self, count: usize) -> Vec<UserInput> {
+        let skip = self.frames.len().saturating_sub(count);
+        self.frames.iter().skip(skip).map(|frame| frame.input.clone()).collect()
+    }
+
+    // Checks the prediction for the acknowledged tick against the server state.
+    // Returns the inputs to replay on top of the server state when they diverge.
+    pub fn reconcile(&mut self, acknowledged_tick: usize, server_state: &ServerState) -> Option<Vec<UserInput>> {
+        while self.frames.front().is_some_and(|frame| frame.input.tick < acknowledged_tick) {
+            self.frames.pop_front();
+        }
+
+        // A missing frame means the prediction can't be trusted, so it counts as diverged
+        let is_matching = match self.frames.front() {
+            Some(frame) if frame.input.tick == acknowledged_tick => {
+                !frame.diverges_from(server_state)
+            },
+            _ => false,
+        };
+        if self.frames.front().is_some_and(|frame| frame.input.tick == acknowledged_tick) {
+            self.frames.pop_front();
+        }
+
+        if is_matching {
+            return None;
+        }
+        Some(self.frames.drain(..).map(|frame| frame.input).collect())
+    }
+
+    // Compares the prediction made with the acknowledged input against the server's hashes.
+    // None when there is no prediction for the same input at the same level frame.
+    pub fn check_checksum(&self, acknowledged_tick: usize, checksum: &StateChecksum) -> Option<ChecksumCheck> {
+        let frame = self.frames.iter().find(|frame| frame.input.tick == acknowledged_tick && frame.level_frame == checksum.level_frame)?;
+        let predicted_checksum = StateChecksum::capture(frame.level_frame, &frame.player, &frame.actor_manager);
+        Some(match predicted_checksum.get_first_divergence(checksum) {
+            Some(field) => ChecksumCheck::Desynced(field),
+            None => ChecksumCheck::Matched,
+        })
+    }
+}
+
+// Authoritative state of the local player and the shared world from one server snapshot
+pub struct ServerState {
+    pub level_frame: u32,
+    pub player: Player,
+    pub harvesters: Vec<Harvester>,
+    pub actor_manager: ActorManager,
+    // Full precision hash of the server state after last_processed_tick, only sent now and then
+    pub checksum: Option<StateChecksum>,
+}
+
+// Checks the prediction against the server and on divergence rolls back to the server state
+// and replays everything predicted after it. The headless harness runs this same path.
+// Returns the result of the checksum comparison when the server sent one.
+pub fn reconcile_local_state(
+    local_player: &mut Player,
+    level: &mut Level,
+    prediction_buffer: &mut PredictionBuffer,
+    last_processed_tick: Option<usize>,
+    server_state: ServerState,
+) -> Option<ChecksumCheck> {
+    // Until the server processed one of our inputs there is nothing to compare the prediction with
+    let last_tick = last_processed_tick?;
+
+    // Same inputs on the same state have to give the same result, anything else is a determinism bug
+    let checksum_check = server_state.checksum.as_ref().and_then(|checksum| prediction_buffer.check_checksum(last_tick, checksum));
+    if let Some(ChecksumCheck::Desynced(field)) = &checksum_check {
+        log!("Desync at input tick {}: {} differs from the server", last_tick, field);
+    }
+
+    if let Some(inputs) = prediction_buffer.reconcile(last_tick, &server_state) {
+        level.frame = server_state.level_frame;
+        *local_player = server_state.player;
+        level.harvesters = server_state.harvesters;
+        level.actor_manager = server_state.actor_manager;
+        for input in &inputs {
+            simulate_frame(local_player, level, input);
+            prediction_buffer.record(input, local_player, level);
+        }
+    }
+    checksum_check
+}
@@ -0,0 +1,288 @@
+use crate::*;
+
+// Frames simulated per update while fast-forwarding
+const FAST_FORWARD_SPEED: u32 = 4;
+const REPLAY_MENU_X: i32 = 63;
+const REPLAY_MENU_FIRST_Y: i32 = 60;
+const REPLAY_MENU_OPTION_HEIGHT: i32 = 22;
+// Menu entry that exports the speedrun history, shown once there is a run to export
+pub const EXPORT_RUNS_OPTION: &str = "Export Runs";
+
+// One bit per button, in UserInput field order
+fn pack_buttons(input: &UserInput) -> u8 {
+    [
+        input.jump_pressed,
+        input.jump_just_pressed,
+        input.left_pressed,
+        input.right_pressed,
+        input.up_pressed,
+        input.down_pressed,
+        input.pick_just_pressed,
+        input.dash_just_pressed,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |buttons, (bit, is_pressed)| buttons | ((*is_pressed as u8) << bit))
+}
+
+fn unpack_buttons(tick: usize, buttons: u8) -> UserInput {
+    let is_pressed = |bit: u8| buttons & (1 << bit) != 0;
+    UserInput {
+        tick,
+        jump_pressed: is_pressed(0),
+        jump_just_pressed: is_pressed(1),
+        left_pressed: is_pressed(2),
+        right_pressed: is_pressed(3),
+        up_pressed: is_pressed(4),
+        down_pressed: is_pressed(5),
+        pick_just_pressed: is_pressed(6),
+        dash_just_pressed: is_pressed(7),
+    }
+}
+
+// Held buttons rarely change between frames, so a run is stored as repeats of the same byte
+#[turbo::serialize]
+struct InputRun {
+    buttons: u8,
+    frame_count: u16,
+}
+
+// Where a run ended up. Playing its inputs back has to reproduce this exactly.
+#[turbo::serialize]
+pub struct ReplayOutcome {
+    pub final_position: Vector2,
+    pub total_flux: f32,
+}
+
+impl ReplayOutcome {
+    pub fn capture(player: &Player, level: &Level) -> Self {
+        Self {
+            final_position: player.get_position(),
+            total_flux: level.get_total_flux(),
+        }
+    }
+
+    pub fn matches(&self, other: &ReplayOutcome) -> bool {
+        self.final_position.x == other.final_position.x
+            && self.final_position.y == other.final_position.y
+            && self.total_flux == other.total_flux
+    }
+}
+
+// A single-player run through one level: where it started and the input of every simulated frame
+#[turbo::serialize]
+pub struct Recording {
+    pub level_name: LevelName,
+    pub movement_profile: String,
+    runs: Vec<InputRun>,
+    frame_count: u32,
+    // Set once the level was completed
+    pub outcome: Option<ReplayOutcome>,
+}
+
+impl Recording {
+    pub fn new(level_name: LevelName, movement_profile: String) -> Self {
+        Self {
+            level_name,
+            movement_profile,
+            runs: vec![],
+            frame_count: 0,
+            outcome: None,
+        }
+    }
+
+    pub fn push(&mut self, input: &UserInput) {
+        let buttons = pack_buttons(input);
+        self.frame_count += 1;
+        match self.runs.last_mut() {
+            Some(run) if run.buttons == buttons && run.frame_count < u16::MAX => run.frame_count += 1,
+            _ => self.runs.push(InputRun { buttons, frame_count: 1 }),
+        }
+    }
+
+    pub fn get_frame_count(&self) -> u32 {
+        self.frame_count
+    }
+}
+
+// Feeds a recording back one frame at a time, with pause, single steps and fast-forward
+#[turbo::serialize]
+pub struct ReplayPlayback {
+    pub recording: Recording,
+    run_index: usize,
+    frame_in_run: u16,
+    frame: u32,
+    is_paused: bool,
+    is_fast_forwarding: bool,
+}
+
+impl ReplayPlayback {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            run_index: 0,
+            frame_in_run: 0,
+            frame: 0,
+            is_paused: false,
+            is_fast_forwarding: false,
+        }
+    }
+
+    pub fn next_input(&mut self) -> Option<UserInput> {
+        let run = self.recording.runs.get(self.run_index)?;
+        let input = unpack_buttons(self.frame as usize, run.buttons);
+        self.frame += 1;
+        self.frame_in_run += 1;
+        if self.frame_in_run >= run.frame_count {
+            self.run_index += 1;
+            self.frame_in_run = 0;
+        }
+        Some(input)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.run_index >= self.recording.runs.len()
+    }
+
+    // Confirm pauses, Right steps a single frame while paused and fast-forwards while held otherwise.
+    // Returns how many frames to play this update.
+    pub fn handle_input(&mut self, input_map: &InputMap) -> u32 {
+        if input_map.just_pressed(Action::Confirm) {
+            self.is_paused = !self.is_paused;
+        }
+        self.is_fast_forwarding = !self.is_paused && input_map.pressed(Action::Right);
+        if self.is_paused {
+            input_map.just_pressed(Action::Right) as u32
+        } else if self.is_fast_forwarding {
+            FAST_FORWARD_SPEED
+        } else {
+            1
+        }
+    }
+
+    pub fn draw(&self, screen_center: &Vector2) {
+        let left = screen_center.x as i32 - SCREEN_WIDTH / 2;
+        let top = screen_center.y as i32 - SCREEN_HEIGHT / 2;
+        let status = if self.is_finished() {
+            "REPLAY END"
+        } else if self.is_paused {
+            "REPLAY PAUSED"
+        } else if self.is_fast_forwarding {
+            "REPLAY >>"
+        } else {
+            "REPLAY"
+        };
+        let progress = format!("{} / {}", format_frames(self.frame), format_frames(self.recording.get_frame_count()));
+        text!(status, x = left + 8, y = top + 8, color = 0xff6060ff, font = "large");
+        text!(&progress, x = left + 8, y = top + 20, color = 0xffffffff, font = "large");
+        text!("Confirm: pause  Right: step/fast  Quit: leave", x = left + 8, y = top + SCREEN_HEIGHT - 16, color = 0x777777ff, font = "large");
+    }
+}
+
+// Simulated frames as m:ss.cc
+pub fn format_frames(frames: u32) -> String {
+    format_millis(frames as u64 * 1000 / 60)
+}
+
+pub fn format_millis(millis: u64) -> String {
+    let hundredths = millis / 10;
+    format!("{}:{:02}.{:02}", hundredths / 6000, hundredths / 100 % 60, hundredths % 100)
+}
+
+// Single-player loop without rendering or the death pause, the part of the game a replay has to reproduce
+#[cfg(any(test, feature = "harness"))]
+pub fn simulate_single_player(player: &mut Player, level_manager: &mut LevelManager, input: &UserInput) {
+    simulate_frame(player, &mut level_manager.loaded_level, input);
+    if player.exposure.is_lethal() || level_manager.loaded_level.has_fallen_out(player) {
+        respawn_single_player(player, level_manager);
+    }
+}
+
+#[cfg(any(test, feature = "harness"))]
+pub fn load_replay_level(level_name: &LevelName) -> (Player, LevelManager) {
+    let mut level_manager = LevelManager::new();
+    level_manager.load_level(level_name.clone());
+    let start_position = level_manager.loaded_level.get_player_start_position(0);
+    (Player::new(start_position.x, start_position.y), level_manager)
+}
+
+// Plays a recording to the end without rendering, for the harness and the tests
+#[cfg(any(test, feature = "harness"))]
+pub fn play_recording_headless(recording: &Recording) -> ReplayOutcome {
+    let (mut player, mut level_manager) = load_replay_level(&recording.level_name);
+    level_manager.loaded_level.movement_profile = get_movement_profile(&recording.movement_profile);
+    let mut playback = ReplayPlayback::new(recording.clone());
+    while let Some(input) = playback.next_input() {
+        simulate_single_player(&mut player, &mut level_manager, &input);
+    }
+    ReplayOutcome::capture(&player, &level_manager.loaded_level)
+}
+
+// One entry per level with a saved run, plus Back
+pub fn get_replay_menu_options(save_data: &SaveData) -> Vec<MenuOption> {
+    let mut texts: Vec<String> = save_data.replays.iter()
+        .map(|recording| recording.level_name.get_display_name().to_string())
+        .collect();
+    texts.sort();
+    if !save_data.speedrun_history.is_empty() {
+        texts.push(String::from(EXPORT_RUNS_OPTION));
+    }
+    texts.push(String::from("Back"));
+    texts.into_iter()
+        .enumerate()
+        .map(|(idx, text)| MenuOption::new(text, REPLAY_MENU_X, REPLAY_MENU_FIRST_Y + REPLAY_MENU_OPTION_HEIGHT * idx as i32, idx == 0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs right with jumps and the odd dash, enough to move harvesters and die now and then
+    fn scripted_input(frame: u32, previous: &UserInput) -> UserInput {
+        let jump_pressed = frame % 45 < 12;
+        UserInput {
+            tick: frame as usize,
+            jump_pressed,
+            jump_just_pressed: jump_pressed && !previous.jump_pressed,
+            left_pressed: frame % 240 >= 220,
+            right_pressed: frame % 240 < 200,
+            dash_just_pressed: frame % 90 == 30,
+            ..UserInput::new()
+        }
+    }
+
+    // Plays the inputs live while recording them, like the game does in single player
+    fn record(level_name: LevelName, frame_count: u32, input_for: impl Fn(u32, &UserInput) -> UserInput) -> Recording {
+        let (mut player, mut level_manager) = load_replay_level(&level_name);
+        let mut recording = Recording::new(level_name, level_manager.loaded_level.movement_profile.name.clone());
+        let mut previous_input = UserInput::new();
+        for frame in 0..frame_count {
+            let input = input_for(frame, &previous_input);
+            simulate_single_player(&mut player, &mut level_manager, &input);
+            recording.push(&input);
+            previous_input = input;
+        }
+        recording.outcome = Some(ReplayOutcome::capture(&player, &level_manager.loaded_level));
+        recording
+    }
+
+    #[test]
+    fn replays_of_every_level_end_where_the_run_did() {
+        for level_name in TIME_ATTACK_LEVELS {
+            let recording = record(level_name.clone(), 900, scripted_input);
+            // Through the save format, like a replay picked from the menu
+            let recording: Recording = borsh::from_slice(&borsh::to_vec(&recording).unwrap()).unwrap();
+            let replayed_outcome = play_recording_headless(&recording);
+            assert!(recording.outcome.unwrap().matches(&replayed_outcome), "replay of {:?} diverged", level_name);
+        }
+    }
+
+    #[test]
+    fn replay_tells_different_runs_apart() {
+        let outcome = play_recording_headless(&record(LevelName::Level1, 300, scripted_input));
+        assert!(outcome.matches(&play_recording_headless(&record(LevelName::Level1, 300, scripted_input))));
+        let idle_outcome = play_recording_headless(&record(LevelName::Level1, 300, |frame, _| UserInput { tick: frame as usize, ..UserInput::new() }));
+        assert!(!outcome.matches(&idle_outcome));
+    }
+}
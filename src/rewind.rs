@@ -0,0 +1,123 @@
+use crate::*;
+
+// Only every other frame is kept, so rewinding plays back at double speed
+const REWIND_FRAME_INTERVAL: u32 = 2;
+
+// How far back a level lets the player rewind
+#[turbo::serialize]
+pub struct RewindSettings {
+    pub history_frames: u32,
+    // Rewinds allowed per visit of the level, None for unlimited
+    pub max_uses: Option<u32>,
+}
+
+impl RewindSettings {
+    pub fn new(history_frames: u32, max_uses: Option<u32>) -> Self {
+        Self {
+            history_frames,
+            max_uses,
+        }
+    }
+}
+
+// The parts of the level a single-player frame can change. Tiles, cores and exits never
+// change, so they're left out to keep the snapshots small.
+#[turbo::serialize]
+struct RewindFrame {
+    level_frame: u32,
+    player: Player,
+    harvesters: Vec<Harvester>,
+    actor_manager: ActorManager,
+    open_doors: Vec<bool>,
+    checkpoints: Vec<Checkpoint>,
+    checkpoint_snapshot: Option<CheckpointSnapshot>,
+}
+
+#[turbo::serialize]
+pub struct RewindBuffer {
+    frames: VecDeque<RewindFrame>,
+    use_count: u32,
+    is_rewinding: bool,
+}
+
+impl RewindBuffer {
+    pub fn new() -> Self {
+        Self {
+            frames: VecDeque::new(),
+            use_count: 0,
+            is_rewinding: false,
+        }
+    }
+
+    pub fn record(&mut self, player: &Player, level: &Level) {
+        if !level.frame.is_multiple_of(REWIND_FRAME_INTERVAL) {
+            return;
+        }
+        self.frames.push_back(RewindFrame {
+            level_frame: level.frame,
+            player: player.clone(),
+            harvesters: level.harvesters.clone(),
+            actor_manager: level.actor_manager.clone(),
+            open_doors: level.tilemap.doors.iter().map(|door| door.open).collect(),
+            checkpoints: level.checkpoints.clone(),
+            checkpoint_snapshot: level.checkpoint_snapshot.clone(),
+        });
+        let capacity = (level.rewind_settings.history_frames / REWIND_FRAME_INTERVAL) as usize;
+        while self.frames.len() > capacity {
+            self.frames.pop_front();
+        }
+    }
+
+    // Holding the button keeps rewinding, each press counts as one use
+    pub fn start(&mut self, settings: &RewindSettings) -> bool {
+        if self.is_rewinding {
+            return true;
+        }
+        if self.frames.is_empty() || settings.max_uses.is_some_and(|max_uses| self.use_count >= max_uses) {
+            return false;
+        }
+        self.use_count += 1;
+        self.is_rewinding = true;
+        true
+    }
+
+    pub fn stop(&mut self) {
+        self.is_rewinding = false;
+    }
+
+    // Restores the latest frame. Once the history runs out the oldest one stays in place.
+    pub fn step_back(&mut self, player: &mut Player, level: &mut Level) {
+        let frame = match self.frames.pop_back() {
+            Some(frame) => frame,
+            None => return,
+        };
+        *player = frame.player;
+        level.frame = frame.level_frame;
+        level.harvesters = frame.harvesters;
+        level.actor_manager = frame.actor_manager;
+        for (door, is_open) in level.tilemap.doors.iter_mut().zip(frame.open_doors) {
+            door.open = is_open;
+        }
+        level.checkpoints = frame.checkpoints;
+        level.checkpoint_snapshot = frame.checkpoint_snapshot;
+    }
+
+    // A respawn starts over, there is nothing before it to go back to
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.is_rewinding = false;
+    }
+
+    pub fn draw(&self, settings: &RewindSettings, screen_center: &Vector2) {
+        if !self.is_rewinding {
+            return;
+        }
+        let left = screen_center.x as i32 - SCREEN_WIDTH / 2;
+        let top = screen_center.y as i32 - SCREEN_HEIGHT / 2;
+        text!("<< REWIND", x = left + 8, y = top + 8, color = 0xffffffff, font = "large");
+        if let Some(max_uses) = settings.max_uses {
+            let uses_left = format!("{} left", max_uses.saturating_sub(self.use_count));
+            text!(&uses_left, x = left + 8, y = top + 20, color = 0x777777ff, font = "large");
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::*;
+
+// Marks saves that start with a version number, older ones start right with the settings
+const SAVE_MAGIC: [u8; 4] = *b"FXSV";
+const SAVE_VERSION: u32 = 3;
+// Oldest speedruns are dropped beyond this
+const MAX_RUN_HISTORY: usize = 20;
+
+// Everything kept in local storage. The host only stores a single blob, so settings and
+// recorded runs are saved together.
+#[turbo::serialize]
+pub struct SaveData {
+    pub settings: Settings,
+    // The latest finished run of each level
+    pub replays: Vec<Recording>,
+    // The fastest time-attack run of each level, raced as a ghost
+    pub time_attack_bests: Vec<TimeAttackRun>,
+    // The fastest completed story run, its splits are shown while running
+    pub speedrun_best: Option<SpeedrunRecord>,
+    pub speedrun_history: Vec<SpeedrunRecord>,
+}
+
+// A time-attack run as saved before version 3, when ghost positions were i16
+#[turbo::serialize]
+struct TimeAttackRunV2 {
+    level_name: LevelName,
+    splits: Vec<u32>,
+    frame_count: u32,
+    ghost: Vec<Option<PlayerPoseV2>>,
+}
+
+#[turbo::serialize]
+struct PlayerPoseV2 {
+    x: i16,
+    y: i16,
+    animation: PlayerAnimation,
+    is_facing_left: bool,
+    is_carrying: bool,
+}
+
+impl TimeAttackRunV2 {
+    fn upgrade(self) -> TimeAttackRun {
+        TimeAttackRun {
+            level_name: self.level_name,
+            splits: self.splits,
+            frame_count: self.frame_count,
+            ghost: self.ghost.into_iter()
+                .map(|pose| pose.map(|pose| PlayerPose::new(QuantizedPosition::from_i16(pose.x, pose.y), pose.animation, pose.is_facing_left, pose.is_carrying)))
+                .collect(),
+        }
+    }
+}
+
+impl SaveData {
+    pub fn new() -> Self {
+        Self {
+            settings: Settings::new(),
+            replays: vec![],
+            time_attack_bests: vec![],
+            speedrun_best: None,
+            speedrun_history: vec![],
+        }
+    }
+
+    pub fn load() -> Self {
+        match local::load() {
+            Ok(data) if !data.is_empty() => match Self::decode(&data) {
+                Ok(save_data) => save_data,
+                Err(err) => {
+                    log!("Could not read save data: {}", err);
+                    Self::new()
+                },
+            },
+            _ => Self::new(),
+        }
+    }
+
+    // Fields are read one by one, so each version only reads what it wrote. Saves from before
+    // the version tag grew a field at a time and end after the last field they knew about.
+    fn decode(data: &[u8]) -> std::io::Result<Self> {
+        use borsh::BorshDeserialize;
+        let (version, mut reader) = match data.strip_prefix(&SAVE_MAGIC) {
+            Some(mut rest) => (u32::deserialize_reader(&mut rest)?, rest),
+            None => (0, data),
+        };
+        if version > SAVE_VERSION {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("unknown save version {}", version)));
+        }
+
+        let mut save_data = Self::new();
+        save_data.settings.bindings = BorshDeserialize::deserialize_reader(&mut reader)?;
+        if version >= 2 {
+            save_data.settings.player2_bindings = BorshDeserialize::deserialize_reader(&mut reader)?;
+        }
+        if reader.is_empty() {
+            return Ok(save_data);
+        }
+        save_data.replays = BorshDeserialize::deserialize_reader(&mut reader)?;
+        if reader.is_empty() {
+            return Ok(save_data);
+        }
+        save_data.time_attack_bests = if version >= 3 {
+            BorshDeserialize::deserialize_reader(&mut reader)?
+        } else {
+            Vec::<TimeAttackRunV2>::deserialize_reader(&mut reader)?.into_iter().map(TimeAttackRunV2::upgrade).collect()
+        };
+        if reader.is_empty() {
+            return Ok(save_data);
+        }
+        save_data.speedrun_best = BorshDeserialize::deserialize_reader(&mut reader)?;
+        save_data.speedrun_history = BorshDeserialize::deserialize_reader(&mut reader)?;
+        Ok(save_data)
+    }
+
+    fn encode(&self) -> std::io::Result<Vec<u8>> {
+        borsh::to_vec(&(SAVE_MAGIC, SAVE_VERSION, self))
+    }
+
+    pub fn save(&self) {
+        match self.encode() {
+            Ok(data) => {
+                if let Err(err) = local::save(&data) {
+                    log!("Could not save data: {}", err);
+                }
+            },
+            Err(err) => log!("Could not serialize save data: {}", err),
+        }
+    }
+
+    pub fn get_time_attack_best(&self, level_name: &LevelName) -> Option<&TimeAttackRun> {
+        self.time_attack_bests.iter().find(|run| run.level_name == *level_name)
+    }
+
+    pub fn save_time_attack_best(run: TimeAttackRun) {
+        let mut save_data = Self::load();
+        save_data.time_attack_bests.retain(|existing| existing.level_name != run.level_name);
+        save_data.time_attack_bests.push(run);
+        save_data.save();
+    }
+
+    pub fn save_replay(recording: Recording) {
+        let mut save_data = Self::load();
+        save_data.replays.retain(|existing| existing.level_name != recording.level_name);
+        save_data.replays.push(recording);
+        save_data.save();
+    }
+
+    pub fn save_speedrun(record: SpeedrunRecord) {
+        let mut save_data = Self::load();
+        if record.is_completed && save_data.speedrun_best.as_ref().is_none_or(|best| record.get_frame_count() < best.get_frame_count()) {
+            save_data.speedrun_best = Some(record.clone());
+        }
+        save_data.speedrun_history.push(record);
+        if save_data.speedrun_history.len() > MAX_RUN_HISTORY {
+            save_data.speedrun_history.remove(0);
+        }
+        save_data.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_recording() -> Recording {
+        let mut recording = Recording::new(LevelName::Level2, DEFAULT_MOVEMENT_PROFILE.to_string());
+        recording.push(&UserInput { right_pressed: true, ..UserInput::new() });
+        recording
+    }
+
+    fn get_test_time_attack_run() -> TimeAttackRun {
+        TimeAttackRun { level_name: LevelName::Level1, splits: vec![120], frame_count: 300, ghost: vec![] }
+    }
+
+    #[test]
+    fn reads_saves_from_before_the_version_tag() {
+        let bindings = vec![Binding { action: Action::Jump, sources: vec![InputSource::Key(Key::K)] }];
+
+        let settings_only = borsh::to_vec(&bindings).unwrap();
+        let save_data = SaveData::decode(&settings_only).unwrap();
+        assert_eq!(save_data.settings.bindings.len(), 1);
+        assert!(save_data.settings.bindings[0].sources == vec![InputSource::Key(Key::K)]);
+
+        let with_replays = borsh::to_vec(&(&bindings, vec![get_test_recording()])).unwrap();
+        let save_data = SaveData::decode(&with_replays).unwrap();
+        assert_eq!(save_data.replays.len(), 1);
+        assert_eq!(save_data.replays[0].get_frame_count(), 1);
+
+        let with_time_attack = borsh::to_vec(&(&bindings, vec![get_test_recording()], vec![get_test_time_attack_run()])).unwrap();
+        let save_data = SaveData::decode(&with_time_attack).unwrap();
+        assert_eq!(save_data.replays.len(), 1);
+        assert_eq!(save_data.time_attack_bests[0].frame_count, 300);
+        assert!(save_data.speedrun_history.is_empty());
+    }
+
+    #[test]
+    fn reads_version_1_saves_without_player2_bindings() {
+        let bindings = vec![Binding { action: Action::Jump, sources: vec![InputSource::Key(Key::K)] }];
+        let empty_history: Vec<SpeedrunRecord> = vec![];
+        let data = borsh::to_vec(&(SAVE_MAGIC, 1u32, &bindings, vec![get_test_recording()], vec![get_test_time_attack_run()], None::<SpeedrunRecord>, empty_history)).unwrap();
+        let save_data = SaveData::decode(&data).unwrap();
+        assert_eq!(save_data.settings.bindings.len(), 1);
+        assert_eq!(save_data.settings.player2_bindings.len(), get_default_gamepad_bindings().len());
+        assert_eq!(save_data.replays.len(), 1);
+    }
+
+    #[test]
+    fn reads_version_2_ghosts_with_i16_positions() {
+        let bindings = vec![Binding { action: Action::Jump, sources: vec![InputSource::Key(Key::K)] }];
+        let pose = PlayerPoseV2 { x: 400, y: -16, animation: PlayerAnimation::Walk, is_facing_left: true, is_carrying: false };
+        let run = TimeAttackRunV2 { level_name: LevelName::Level1, splits: vec![], frame_count: 2, ghost: vec![Some(pose), None] };
+        let empty_history: Vec<SpeedrunRecord> = vec![];
+        let data = borsh::to_vec(&(SAVE_MAGIC, 2u32, &bindings, &bindings, Vec::<Recording>::new(), vec![run], None::<SpeedrunRecord>, empty_history)).unwrap();
+
+        let save_data = SaveData::decode(&data).unwrap();
+        let ghost = &save_data.time_attack_bests[0].ghost;
+        assert_eq!(ghost.len(), 2);
+        let expected_pose = PlayerPose::new(QuantizedPosition::from_vector(&Vector2::new(50., -2.)), PlayerAnimation::Walk, true, false);
+        assert!(is_same_bytes(&ghost[0], &Some(expected_pose)));
+        assert!(ghost[1].is_none());
+    }
+
+    #[test]
+    fn round_trips_the_current_version() {
+        let mut save_data = SaveData::new();
+        save_data.replays.push(get_test_recording());
+        save_data.settings.player2_bindings = vec![Binding { action: Action::Dash, sources: vec![InputSource::Button(GamepadButton::X)] }];
+        save_data.time_attack_bests.push(get_test_time_attack_run());
+        save_data.speedrun_history.push(SpeedrunRecord { splits: vec![], real_time_ms: 1000, is_completed: false });
+
+        let decoded = SaveData::decode(&save_data.encode().unwrap()).unwrap();
+        assert_eq!(decoded.replays.len(), 1);
+        assert_eq!(decoded.time_attack_bests[0].splits, vec![120]);
+        assert!(decoded.settings.player2_bindings[0].sources == vec![InputSource::Button(GamepadButton::X)]);
+        assert_eq!(decoded.speedrun_history[0].real_time_ms, 1000);
+    }
+}
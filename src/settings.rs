@@ -0,0 +1,42 @@
+use crate::*;
+
+// Player preferences persisted in local storage
+#[turbo::serialize]
+pub struct Settings {
+    pub bindings: Vec<Binding>,
+    // The second local player only has a gamepad
+    pub player2_bindings: Vec<Binding>,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            bindings: get_default_bindings(),
+            player2_bindings: get_default_gamepad_bindings(),
+        }
+    }
+
+    // Falls back to the defaults when nothing was saved yet or the saved data is outdated
+    pub fn load() -> Self {
+        let mut settings = SaveData::load().settings;
+        add_missing_bindings(&mut settings.bindings, get_default_bindings());
+        add_missing_bindings(&mut settings.player2_bindings, get_default_gamepad_bindings());
+        settings
+    }
+
+    // Keeps everything else in the save as it is
+    pub fn save(&self) {
+        let mut save_data = SaveData::load();
+        save_data.settings = self.clone();
+        save_data.save();
+    }
+}
+
+// Actions added after the bindings were saved start out with their defaults
+fn add_missing_bindings(bindings: &mut Vec<Binding>, defaults: Vec<Binding>) {
+    for binding in defaults {
+        if !bindings.iter().any(|existing| existing.action == binding.action) {
+            bindings.push(binding);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+// Deterministic random numbers for the simulation. Seeded the same way, it gives the same
+// sequence on the client and the server, and it rolls back with the state it lives in.
+#[turbo::serialize]
+#[derive(Copy)]
+pub struct SimRng {
+    state: u32,
+}
+
+impl SimRng {
+    pub fn new(seed: u32) -> Self {
+        // Xorshift never leaves zero
+        Self { state: seed.max(1) }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+}
@@ -0,0 +1,296 @@
+use crate::*;
+
+// Positions go over the wire in 1/8 pixel steps, i32 leaves room for levels of any size
+const POSITION_SCALE: f32 = 8.;
+const SNAPSHOT_HISTORY_SIZE: usize = 32;
+
+#[turbo::serialize]
+#[derive(Copy, PartialEq)]
+pub struct QuantizedPosition {
+    x: i32,
+    y: i32,
+}
+
+impl QuantizedPosition {
+    pub fn from_vector(vector: &Vector2) -> Self {
+        Self {
+            x: (vector.x * POSITION_SCALE).round() as i32,
+            y: (vector.y * POSITION_SCALE).round() as i32,
+        }
+    }
+
+    // Saves before version 3 stored positions at the same scale as i16
+    pub fn from_i16(x: i16, y: i16) -> Self {
+        Self {
+            x: x as i32,
+            y: y as i32,
+        }
+    }
+
+    pub fn to_vector(self) -> Vector2 {
+        Vector2::new(self.x as f32 / POSITION_SCALE, self.y as f32 / POSITION_SCALE)
+    }
+}
+
+fn quantize(vector: &Vector2) -> Vector2 {
+    QuantizedPosition::from_vector(vector).to_vector()
+}
+
+pub fn is_same_bytes<T: borsh::BorshSerialize>(a: &T, b: &T) -> bool {
+    match (borsh::to_vec(a), borsh::to_vec(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+// Everything a client needs to rebuild the shared game state at one server tick
+#[turbo::serialize]
+pub struct WorldSnapshot {
+    pub server_tick: u32,
+    // Level clock after the last simulated frame, the flux cores depend on it
+    pub level_frame: u32,
+    // Keyed by player id
+    pub players: BTreeMap<String, Player>,
+    pub harvesters: Vec<Harvester>,
+    pub actor_manager: ActorManager,
+}
+
+impl WorldSnapshot {
+    // Positions are quantized here so the server diffs against exactly what clients rebuild
+    pub fn capture(server_tick: u32, players: BTreeMap<String, Player>, level: &Level) -> Self {
+        let mut snapshot = Self {
+            server_tick,
+            level_frame: level.frame,
+            players,
+            harvesters: level.harvesters.clone(),
+            actor_manager: level.actor_manager.clone(),
+        };
+        for player in snapshot.players.values_mut() {
+            player.actor.position = quantize(&player.actor.position);
+        }
+        for actor in snapshot.actor_manager.actors.values_mut() {
+            actor.position = quantize(&actor.position);
+        }
+        snapshot
+    }
+
+    pub fn diff(&self, baseline: Option<&WorldSnapshot>) -> SnapshotDelta {
+        let baseline = match baseline {
+            Some(baseline) if baseline.harvesters.len() == self.harvesters.len()
+                && baseline.actor_manager.actors.len() == self.actor_manager.actors.len() => baseline,
+            _ => return SnapshotDelta::Full(self.clone()),
+        };
+
+        let mut actors = vec![];
+        for (id, actor) in &self.actor_manager.actors {
+            match baseline.actor_manager.get_actor(*id) {
+                Some(baseline_actor) => {
+                    let position = QuantizedPosition::from_vector(&actor.position);
+                    let is_changed = position != QuantizedPosition::from_vector(&baseline_actor.position)
+                        || !is_same_bytes(&actor.remainder, &baseline_actor.remainder)
+                        || baseline_actor.is_child != actor.is_child;
+                    if is_changed {
+                        actors.push(ActorDelta {
+                            id: *id,
+                            position,
+                            remainder: actor.remainder,
+                            is_child: actor.is_child,
+                        });
+                    }
+                },
+                // Actors are only spawned on level load, so a new id means the baseline is from another level
+                None => return SnapshotDelta::Full(self.clone()),
+            }
+        }
+
+        let harvesters = self.harvesters.iter().enumerate()
+            .filter(|(idx, harvester)| !is_same_bytes(*harvester, &baseline.harvesters[*idx]))
+            .map(|(idx, harvester)| (idx as u32, harvester.clone()))
+            .collect();
+
+        // Players that joined since the baseline are sent whole
+        let players = self.players.iter()
+            .filter_map(|(id, player)| player.diff(baseline.players.get(id)))
+            .collect();
+        let removed_players = baseline.players.keys()
+            .filter(|id| !self.players.contains_key(*id))
+            .cloned()
+            .collect();
+
+        SnapshotDelta::Delta {
+            server_tick: self.server_tick,
+            baseline_tick: baseline.server_tick,
+            level_frame: self.level_frame,
+            players,
+            removed_players,
+            harvesters,
+            actors,
+        }
+    }
+}
+
+#[turbo::serialize]
+pub struct ActorDelta {
+    pub id: ActorId,
+    pub position: QuantizedPosition,
+    // Sub-pixel movement decides the next pixel step, predictions drift without it
+    pub remainder: Vector2,
+    pub is_child: bool,
+}
+
+#[turbo::serialize]
+pub enum SnapshotDelta {
+    // Sent when the client has no usable baseline yet
+    Full(WorldSnapshot),
+    // Only what changed since the snapshot the client acknowledged
+    Delta {
+        server_tick: u32,
+        baseline_tick: u32,
+        level_frame: u32,
+        players: Vec<PlayerDelta>,
+        removed_players: Vec<String>,
+        harvesters: Vec<(u32, Harvester)>,
+        actors: Vec<ActorDelta>,
+    },
+}
+
+#[turbo::serialize]
+pub struct SnapshotHistory {
+    snapshots: VecDeque<WorldSnapshot>,
+}
+
+impl SnapshotHistory {
+    pub fn new() -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, snapshot: WorldSnapshot) {
+        self.snapshots.push_back(snapshot);
+        if self.snapshots.len() > SNAPSHOT_HISTORY_SIZE {
+            self.snapshots.pop_front();
+        }
+    }
+
+    pub fn get(&self, server_tick: u32) -> Option<&WorldSnapshot> {
+        self.snapshots.iter().find(|snapshot| snapshot.server_tick == server_tick)
+    }
+
+    pub fn get_latest_tick(&self) -> Option<u32> {
+        self.snapshots.back().map(|snapshot| snapshot.server_tick)
+    }
+
+    // Rebuilds the full snapshot from a delta, None when its baseline is no longer known
+    pub fn decode(&mut self, delta: SnapshotDelta) -> Option<WorldSnapshot> {
+        let snapshot = match delta {
+            SnapshotDelta::Full(snapshot) => snapshot,
+            SnapshotDelta::Delta { server_tick, baseline_tick, level_frame, players, removed_players, harvesters, actors } => {
+                let mut snapshot = self.get(baseline_tick)?.clone();
+                snapshot.server_tick = server_tick;
+                snapshot.level_frame = level_frame;
+                for player_delta in players {
+                    player_delta.apply(&mut snapshot.players);
+                }
+                for id in removed_players {
+                    snapshot.players.remove(&id);
+                }
+                for (idx, harvester) in harvesters {
+                    if let Some(existing) = snapshot.harvesters.get_mut(idx as usize) {
+                        *existing = harvester;
+                    }
+                }
+                for actor_delta in actors {
+                    if let Some(actor) = snapshot.actor_manager.get_actor_mut(actor_delta.id) {
+                        actor.position = actor_delta.position.to_vector();
+                        actor.remainder = actor_delta.remainder;
+                        actor.is_child = actor_delta.is_child;
+                    }
+                }
+                snapshot
+            },
+        };
+
+        // Older snapshots can't serve as a baseline once a newer one was acknowledged
+        if self.get_latest_tick().is_some_and(|latest_tick| latest_tick >= snapshot.server_tick) {
+            return None;
+        }
+        self.push(snapshot.clone());
+        Some(snapshot)
+    }
+}
+
+// Logs the snapshot traffic once per second next to what full states would have cost
+#[turbo::serialize]
+pub struct BandwidthMeter {
+    sent_bytes: usize,
+    full_state_bytes: usize,
+    elapsed_ms: u32,
+}
+
+impl BandwidthMeter {
+    pub fn new() -> Self {
+        Self {
+            sent_bytes: 0,
+            full_state_bytes: 0,
+            elapsed_ms: 0,
+        }
+    }
+
+    pub fn record(&mut self, sent_bytes: usize, full_state_bytes: usize) {
+        self.sent_bytes += sent_bytes;
+        self.full_state_bytes += full_state_bytes;
+    }
+
+    // Bytes per second actually sent and what full states would have cost
+    pub fn get_rates(&self) -> (u32, u32) {
+        let seconds = self.elapsed_ms.max(1) as f32 / 1000.;
+        ((self.sent_bytes as f32 / seconds) as u32, (self.full_state_bytes as f32 / seconds) as u32)
+    }
+
+    pub fn tick(&mut self, interval_ms: u32) {
+        self.elapsed_ms += interval_ms;
+        if self.elapsed_ms >= 1000 {
+            let (sent_rate, full_state_rate) = self.get_rates();
+            log!("Snapshots: {} B/s, full states would be {} B/s", sent_rate, full_state_rate);
+            *self = Self::new();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A second of a player running right through level 1, sent the way the server sends it
+    #[test]
+    fn deltas_cost_less_than_full_states() {
+        let mut level = construct_level_1();
+        let start_position = level.get_player_start_position(0);
+        let mut player = Player::new_with_id("runner".to_string(), start_position.x, start_position.y);
+        let mut server_history = SnapshotHistory::new();
+        let mut client_history = SnapshotHistory::new();
+        let mut bandwidth_meter = BandwidthMeter::new();
+        let tick_count = 1000 / SERVER_TICK_MS;
+
+        for tick in 0..tick_count {
+            let input = UserInput { tick: tick as usize, right_pressed: true, jump_pressed: tick.is_multiple_of(20), ..UserInput::new() };
+            simulate_server_frame(&mut [&mut player], &[input], &mut level);
+            let players = BTreeMap::from([(player.id.clone(), player.clone())]);
+            let snapshot = WorldSnapshot::capture(tick, players, &level);
+
+            // The client acknowledges every snapshot right away
+            let delta = snapshot.diff(tick.checked_sub(1).and_then(|tick| server_history.get(tick)));
+            let full_state_bytes = borsh::to_vec(&SnapshotDelta::Full(snapshot.clone())).unwrap().len();
+            bandwidth_meter.record(borsh::to_vec(&delta).unwrap().len(), full_state_bytes);
+            bandwidth_meter.elapsed_ms += SERVER_TICK_MS;
+
+            let decoded = client_history.decode(delta).unwrap();
+            assert!(is_same_bytes(&decoded, &snapshot));
+            server_history.push(snapshot);
+        }
+
+        let (sent_rate, full_state_rate) = bandwidth_meter.get_rates();
+        assert!(sent_rate < full_state_rate, "{} B/s sent, full states {} B/s", sent_rate, full_state_rate);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::*;
+
+const RUN_HISTORY_FILE_NAME: &str = "flux-runs.csv";
+
+#[turbo::serialize]
+pub struct LevelSplit {
+    pub level_name: LevelName,
+    // In-game frames spent in this level
+    pub frame_count: u32,
+    pub deaths: u32,
+    // Deaths without a reached checkpoint, which start the level over
+    pub restarts: u32,
+}
+
+impl LevelSplit {
+    fn new(level_name: LevelName) -> Self {
+        Self {
+            level_name,
+            frame_count: 0,
+            deaths: 0,
+            restarts: 0,
+        }
+    }
+}
+
+// One story run from START, finished or abandoned
+#[turbo::serialize]
+pub struct SpeedrunRecord {
+    pub splits: Vec<LevelSplit>,
+    // Real time from START to the end, menus and pauses included
+    pub real_time_ms: u64,
+    pub is_completed: bool,
+}
+
+impl SpeedrunRecord {
+    // In-game time, only frames actually played
+    pub fn get_frame_count(&self) -> u32 {
+        self.splits.iter().map(|split| split.frame_count).sum()
+    }
+}
+
+#[turbo::serialize]
+pub struct SpeedrunTimer {
+    completed_splits: Vec<LevelSplit>,
+    current_split: LevelSplit,
+    started_at_ms: u64,
+    // Personal best to compare the splits against
+    best: Option<SpeedrunRecord>,
+}
+
+impl SpeedrunTimer {
+    pub fn new(first_level: LevelName, best: Option<SpeedrunRecord>) -> Self {
+        Self {
+            completed_splits: vec![],
+            current_split: LevelSplit::new(first_level),
+            started_at_ms: time::now(),
+            best,
+        }
+    }
+
+    // Called once per played frame, so the in-game time stands still in menus
+    pub fn tick(&mut self) {
+        self.current_split.frame_count += 1;
+    }
+
+    pub fn add_death(&mut self, is_restart: bool) {
+        self.current_split.deaths += 1;
+        if is_restart {
+            self.current_split.restarts += 1;
+        }
+    }
+
+    pub fn complete_level(&mut self, next_level: LevelName) {
+        let split = std::mem::replace(&mut self.current_split, LevelSplit::new(next_level));
+        self.completed_splits.push(split);
+    }
+
+    // The level in progress counts as a split only when the run was completed
+    pub fn finish(mut self, is_completed: bool) -> SpeedrunRecord {
+        if is_completed {
+            self.completed_splits.push(self.current_split);
+        }
+        SpeedrunRecord {
+            splits: self.completed_splits,
+            real_time_ms: time::now().saturating_sub(self.started_at_ms),
+            is_completed,
+        }
+    }
+
+    fn get_frame_count(&self) -> u32 {
+        self.completed_splits.iter().map(|split| split.frame_count).sum::<u32>() + self.current_split.frame_count
+    }
+
+    // Personal best time at the end of the given split, counted from the start of the run
+    fn get_best_cumulative_frames(&self, split_index: usize) -> Option<u32> {
+        let best = self.best.as_ref()?;
+        if split_index >= best.splits.len() {
+            return None;
+        }
+        Some(best.splits.iter().take(split_index + 1).map(|split| split.frame_count).sum())
+    }
+
+    pub fn draw(&self, screen_center: &Vector2) {
+        let right = screen_center.x as i32 + SCREEN_WIDTH / 2;
+        let top = screen_center.y as i32 - SCREEN_HEIGHT / 2;
+        let draw_right_aligned = |line: &str, row: i32, color: u32| {
+            text!(line, x = right - 8 - line.len() as i32 * 8, y = top + 8 + row * 12, color = color, font = "large");
+        };
+
+        draw_right_aligned(&format!("IGT {}", format_frames(self.get_frame_count())), 0, 0xffffffff);
+        draw_right_aligned(&format!("RTA {}", format_millis(time::now().saturating_sub(self.started_at_ms))), 1, 0x777777ff);
+        draw_right_aligned(
+            &format!("{} {}", self.current_split.level_name.get_display_name(), format_frames(self.current_split.frame_count)),
+            2,
+            0xffffffff,
+        );
+
+        // Ahead or behind the personal best as of the last finished level
+        let last_index = self.completed_splits.len().checked_sub(1);
+        if let Some(best_frames) = last_index.and_then(|idx| self.get_best_cumulative_frames(idx)) {
+            let frames: u32 = self.completed_splits.iter().map(|split| split.frame_count).sum();
+            let (delta, color) = format_delta(frames, best_frames);
+            draw_right_aligned(&delta, 3, color);
+        }
+        if self.current_split.deaths > 0 {
+            draw_right_aligned(&format!("Deaths {}", self.current_split.deaths), 4, 0x777777ff);
+        }
+    }
+}
+
+// Hands the run history to the host as a CSV file. In the browser the page receives it as a
+// game event and can save it, other hosts only get it through the log.
+pub fn export_run_history(history: &[SpeedrunRecord]) {
+    let csv = export_run_history_csv(history);
+    events::emit("exportRunHistory", &json!({ "filename": RUN_HISTORY_FILE_NAME, "csv": csv }).to_string());
+    log!("{}", csv);
+}
+
+// One line per level of every run, for pasting into a spreadsheet
+pub fn export_run_history_csv(history: &[SpeedrunRecord]) -> String {
+    let mut csv = String::from("run,completed,real_time,level,level_time,deaths,restarts\n");
+    for (run_idx, record) in history.iter().enumerate() {
+        for split in &record.splits {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                run_idx + 1,
+                record.is_completed,
+                format_millis(record.real_time_ms),
+                split.level_name.get_display_name(),
+                format_frames(split.frame_count),
+                split.deaths,
+                split.restarts,
+            ));
+        }
+    }
+    csv
+}
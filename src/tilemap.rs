@@ -4,6 +4,8 @@ use crate::*;
 
 use sys::time::tick;
 
+const EDGE_WALL_WIDTH: f32 = 16.;
+
 #[turbo::serialize]
 pub struct TileMap {
     pub tiles: Vec<Tile>,
@@ -116,11 +118,20 @@ impl TileMap {
         Vector2::new(position.x.clamp(min_x, max_x), position.y.clamp(min_y, max_y))
     } 
 
-    pub fn draw_flux_field(&self) {
+    pub fn get_bound(&self) -> BoundingBox {
+        BoundingBox {
+            top: 0.,
+            right: self.width,
+            bottom: self.height,
+            left: 0.,
+        }
+    }
+
+    pub fn draw_flux_field(&self, frame: u32) {
         for i in (0..self.width as i32).step_by(16) {
-            for j in (-self.height as i32..self.height as i32).step_by(16) {
+            for j in (0..self.height as i32).step_by(16) {
                 let point = Vector2::new(i as f32, j as f32);
-                let net_flux = net_flux_field_at_point(&point, &self.flux_cores);
+                let net_flux = net_flux_field_at_point(&point, &self.flux_cores, frame);
                 if net_flux.length() > 6.0 {
                     net_flux.draw_at_point(&point, f32::sqrt(0.4 * (tick() % 80) as f32 / 80.));
                 }
@@ -128,7 +139,18 @@ impl TileMap {
         }
     }
     
-    pub fn is_inside(&self, position: &Vector2) -> bool {
-        position.x > 0. && position.x < self.width
+    pub fn is_below(&self, position: &Vector2) -> bool {
+        position.y > self.get_bound().bottom
+    }
+
+    // Levels are left through exit zones, so the sides of the map block like walls.
+    // They reach well above the top so jumping can't get past them either.
+    pub fn get_edge_walls(&self) -> [Solid; 2] {
+        let wall = |x: f32| Solid {
+            position: Vector2::new(x, self.height / 2. - self.height),
+            width: EDGE_WALL_WIDTH,
+            height: self.height * 3.,
+        };
+        [wall(-EDGE_WALL_WIDTH / 2.), wall(self.width + EDGE_WALL_WIDTH / 2.)]
     }
 }
\ No newline at end of file
@@ -0,0 +1,143 @@
+use crate::*;
+
+const GHOST_TINT: u32 = 0x8cf0ff80;
+const AHEAD_COLOR: u32 = 0x60ff80ff;
+const BEHIND_COLOR: u32 = 0xff6060ff;
+const MENU_X: i32 = 63;
+const FIRST_OPTION_Y: i32 = 60;
+const OPTION_HEIGHT: i32 = 22;
+pub const TIME_ATTACK_LEVELS: [LevelName; 4] = [LevelName::Level1, LevelName::Level2, LevelName::Level3, LevelName::Level4];
+
+// A finished time-attack run. The fastest one per level is kept as the personal best.
+#[turbo::serialize]
+pub struct TimeAttackRun {
+    pub level_name: LevelName,
+    // Frames from the start of the level to each newly reached checkpoint
+    pub splits: Vec<u32>,
+    pub frame_count: u32,
+    // Where the player was on every frame, None while dead
+    pub ghost: Vec<Option<PlayerPose>>,
+}
+
+// The attempt in progress, raced against the personal best when there is one
+#[turbo::serialize]
+pub struct TimeAttack {
+    run: TimeAttackRun,
+    best: Option<TimeAttackRun>,
+}
+
+impl TimeAttack {
+    pub fn new(level_name: LevelName, best: Option<TimeAttackRun>) -> Self {
+        Self {
+            run: TimeAttackRun {
+                level_name,
+                splits: vec![],
+                frame_count: 0,
+                ghost: vec![],
+            },
+            best,
+        }
+    }
+
+    // Called once per in-game frame, death sequences included since they cost time too
+    pub fn record_frame(&mut self, player: &Player, is_alive: bool, level: &Level) {
+        self.run.frame_count += 1;
+        self.run.ghost.push(if is_alive { Some(player.get_pose()) } else { None });
+
+        // Checkpoints reset when the level restarts, only a new furthest one counts as a split
+        let reached_count = level.checkpoints.iter().filter(|checkpoint| checkpoint.is_reached).count();
+        if reached_count > self.run.splits.len() {
+            self.run.splits.push(self.run.frame_count);
+        }
+    }
+
+    pub fn draw_ghost(&self) {
+        let frame = self.run.frame_count as usize;
+        if let Some(Some(pose)) = self.best.as_ref().and_then(|best| best.ghost.get(frame)) {
+            pose.draw(GHOST_TINT);
+        }
+    }
+
+    pub fn draw_timer(&self, screen_center: &Vector2) {
+        let right = screen_center.x as i32 + SCREEN_WIDTH / 2;
+        let top = screen_center.y as i32 - SCREEN_HEIGHT / 2;
+        let time = format_frames(self.run.frame_count);
+        text!(&time, x = right - 8 - time.len() as i32 * 8, y = top + 8, color = 0xffffffff, font = "large");
+
+        // Difference at the latest split, the ghost shows the rest
+        let latest_split = self.run.splits.len().checked_sub(1);
+        let best_split = latest_split.and_then(|idx| self.best.as_ref()?.splits.get(idx).copied());
+        if let (Some(idx), Some(best_split)) = (latest_split, best_split) {
+            let (delta, color) = format_delta(self.run.splits[idx], best_split);
+            text!(&delta, x = right - 8 - delta.len() as i32 * 8, y = top + 20, color = color, font = "large");
+        }
+    }
+
+    // Ends the attempt at the exit
+    pub fn finish(self) -> TimeAttackResults {
+        let is_new_best = self.best.as_ref().is_none_or(|best| self.run.frame_count < best.frame_count);
+        TimeAttackResults { run: self.run, best: self.best, is_new_best }
+    }
+}
+
+// The run against the personal best it was raced against, shown after the exit
+#[turbo::serialize]
+pub struct TimeAttackResults {
+    pub run: TimeAttackRun,
+    best: Option<TimeAttackRun>,
+    pub is_new_best: bool,
+}
+
+impl TimeAttackResults {
+    pub fn draw(&self) {
+        let title = format!("{} - TIME ATTACK", self.run.level_name.get_display_name());
+        text!(&title, x = SCREEN_WIDTH / 2 - title.len() as i32 * 4, y = 30, color = 0xffffffff, font = "large");
+
+        let mut rows: Vec<(String, u32, Option<u32>)> = self.run.splits.iter()
+            .enumerate()
+            .map(|(idx, split)| (format!("Checkpoint {}", idx + 1), *split, self.best.as_ref().and_then(|best| best.splits.get(idx).copied())))
+            .collect();
+        rows.push((String::from("Finish"), self.run.frame_count, self.best.as_ref().map(|best| best.frame_count)));
+
+        for (idx, (name, frames, best_frames)) in rows.iter().enumerate() {
+            let y = 60 + idx as i32 * 16;
+            text!(name, x = 80, y = y, color = 0xffffffff, font = "large");
+            let time = format_frames(*frames);
+            text!(&time, x = 250, y = y, color = 0xffffffff, font = "large");
+            if let Some(best_frames) = best_frames {
+                let (delta, color) = format_delta(*frames, *best_frames);
+                text!(&delta, x = 350, y = y, color = color, font = "large");
+            }
+        }
+
+        if self.is_new_best {
+            text!("New personal best!", x = SCREEN_WIDTH / 2 - 72, y = SCREEN_HEIGHT - 64, color = AHEAD_COLOR, font = "large");
+        }
+        text!("Confirm to retry, back for levels", x = SCREEN_WIDTH / 2 - 132, y = SCREEN_HEIGHT - 24, color = 0x777777ff, font = "large");
+    }
+}
+
+// Time against the ghost, negative and green when ahead
+pub fn format_delta(frames: u32, best_frames: u32) -> (String, u32) {
+    if frames <= best_frames {
+        (format!("-{}", format_frames(best_frames - frames)), AHEAD_COLOR)
+    } else {
+        (format!("+{}", format_frames(frames - best_frames)), BEHIND_COLOR)
+    }
+}
+
+// One entry per level with its personal best, plus Back
+pub fn get_time_attack_menu_options(save_data: &SaveData) -> Vec<MenuOption> {
+    let mut texts: Vec<String> = TIME_ATTACK_LEVELS.iter()
+        .map(|level_name| {
+            let best_time = save_data.get_time_attack_best(level_name)
+                .map_or(String::from("--:--.--"), |best| format_frames(best.frame_count));
+            format!("{}  {}", level_name.get_display_name(), best_time)
+        })
+        .collect();
+    texts.push(String::from("Back"));
+    texts.into_iter()
+        .enumerate()
+        .map(|(idx, text)| MenuOption::new(text, MENU_X, FIRST_OPTION_Y + OPTION_HEIGHT * idx as i32, idx == 0))
+        .collect()
+}
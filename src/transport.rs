@@ -0,0 +1,51 @@
+use crate::*;
+
+// Outgoing server messages go through here so the headless harness can pick them up
+// instead of handing them to turbo-os.
+
+// (recipient, borsh bytes), a recipient of None is a broadcast
+#[cfg(feature = "harness")]
+pub type CapturedMessage = (Option<String>, Vec<u8>);
+
+#[cfg(feature = "harness")]
+thread_local! {
+    static CAPTURED_MESSAGES: std::cell::RefCell<Option<Vec<CapturedMessage>>> = const { std::cell::RefCell::new(None) };
+}
+
+#[cfg(feature = "harness")]
+pub fn start_capturing_messages() {
+    CAPTURED_MESSAGES.with(|messages| *messages.borrow_mut() = Some(vec![]));
+}
+
+#[cfg(feature = "harness")]
+pub fn take_captured_messages() -> Vec<CapturedMessage> {
+    CAPTURED_MESSAGES.with(|messages| messages.borrow_mut().as_mut().map(std::mem::take).unwrap_or_default())
+}
+
+// Returns true when the message was captured
+#[cfg(feature = "harness")]
+fn capture<T: borsh::BorshSerialize>(recipient: Option<&str>, data: &T) -> bool {
+    CAPTURED_MESSAGES.with(|messages| match messages.borrow_mut().as_mut() {
+        Some(messages) => {
+            messages.push((recipient.map(String::from), borsh::to_vec(data).unwrap_or_default()));
+            true
+        },
+        None => false,
+    })
+}
+
+pub fn send_to_client<T: borsh::BorshSerialize>(user_id: &str, data: T) -> Result<(), std::io::Error> {
+    #[cfg(feature = "harness")]
+    if capture(Some(user_id), &data) {
+        return Result::Ok(());
+    }
+    os::server::channel::send(user_id, data)
+}
+
+pub fn broadcast_to_clients<T: borsh::BorshSerialize>(data: T) -> Result<(), std::io::Error> {
+    #[cfg(feature = "harness")]
+    if capture(None, &data) {
+        return Result::Ok(());
+    }
+    os::server::channel::broadcast(data)
+}